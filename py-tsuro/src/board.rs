@@ -1,6 +1,9 @@
+use std::collections::VecDeque;
 use std::vec;
 
 use itertools::Itertools;
+use pyo3_tch::PyTensor;
+use tch::Tensor;
 
 #[cfg(test)]
 use crate::find_tile_with_connection;
@@ -9,31 +12,62 @@ use crate::*;
 
 mod graph;
 mod marker;
+mod render;
+mod zobrist;
+
+pub(crate) use graph::GraphPatch;
 
 impl Board {
     pub fn new() -> Self {
         Self {
             markers: vec![],
-            tiles: [[None; 6]; 6],
+            tiles: [[None; BOARD_SIZE]; BOARD_SIZE],
             graph: BoardGraph::new(),
         }
     }
 }
 
 impl Board {
+    /// data of tile_tensor, flattened
+    pub(crate) fn tile_one_hot(&self) -> Vec<i8> {
+        let mut one_hot = vec![0i8; BOARD_SIZE * BOARD_SIZE * 36];
+        for (x, y) in (0..BOARD_SIZE).cartesian_product(0..BOARD_SIZE) {
+            let channel = match self.tiles[x][y] {
+                Some(tile) => tile
+                    .index()
+                    .expect("placed tiles should be rotations of ALL_TILES"),
+                None => 35,
+            };
+            one_hot[(x * BOARD_SIZE + y) * 36 + channel] = 1;
+        }
+        one_hot
+    }
+
+    /// position_index is one of the 0..NUM_EDGE_POSITIONS edge positions,
+    /// returns false if it is out of range or already taken
     pub fn place_marker(&mut self, position_index: usize) -> bool {
-        let marker = Some(Marker {
-            position: MarkerPosition::from_index(position_index),
-            previous_tile: None,
-            has_moved: false,
-        });
-        if self.markers.contains(&marker) {
+        if position_index >= NUM_EDGE_POSITIONS {
             return false;
         }
-        self.markers.push(marker);
+        let position = MarkerPosition::from_index(position_index);
+        if self.position_is_taken(position) {
+            return false;
+        }
+        self.markers.push(Some(Marker {
+            position,
+            previous_tile: None,
+            has_moved: false,
+        }));
         true
     }
 
+    /// wether a marker that has not been eliminated is on the position
+    pub(crate) fn position_is_taken(&self, position: MarkerPosition) -> bool {
+        self.markers
+            .iter()
+            .any(|marker| marker.is_some_and(|m| m.position == position))
+    }
+
     pub fn place_tile(&mut self, tile: Tile, player: usize) {
         let (x, y) = self.next_tile_of_player(player);
         let old_tile = &mut self.tiles[x][y];
@@ -42,6 +76,40 @@ impl Board {
         *old_tile = Some(tile);
     }
 
+    /// the board with every tile, cell and lattice position mapped,
+    /// the graph is rebuilt from the mapped tiles
+    fn transformed(
+        &self,
+        map_cell: impl Fn(Coord) -> Coord,
+        map_lattice: impl Fn(Coord) -> Coord,
+        map_tile: impl Fn(Tile) -> Tile,
+    ) -> Board {
+        let mut board = Board::new();
+        for (x, y) in (0..BOARD_SIZE).cartesian_product(0..BOARD_SIZE) {
+            let Some(tile) = self.tiles[x][y] else {
+                continue;
+            };
+            let (new_x, new_y) = map_cell((x, y));
+            let tile = map_tile(tile);
+            board.graph.place_tile(tile, (new_x, new_y));
+            board.tiles[new_x][new_y] = Some(tile);
+        }
+        board.markers = self
+            .markers
+            .iter()
+            .map(|marker| {
+                marker.map(|marker| Marker {
+                    position: MarkerPosition::from_lattice_coordinates(map_lattice(
+                        marker.position.coords,
+                    )),
+                    previous_tile: marker.previous_tile.map(&map_cell),
+                    has_moved: marker.has_moved,
+                })
+            })
+            .collect();
+        board
+    }
+
     /// returns the players that would collide if the tile was placed in the position
     /// works if the tile has already been placed provided the pawns have not been moved yet
     pub fn find_collisions(&self, tile: Tile, position: Coord) -> Vec<usize> {
@@ -60,6 +128,8 @@ impl Board {
             .collect_vec();
 
         // Check if two players have pawns on two ends of a path on the newly placed tile
+        // a player can not collide with itself, a path with the same pawn on both
+        // ends does not eliminate it
         for (entry_idx_a, entry_idx_b) in tile.paths() {
             let player_a = marker_positions
                 .iter()
@@ -69,6 +139,9 @@ impl Board {
                 .position(|pos| *pos == Some(positions[entry_idx_b]));
 
             if let (Some(player_a), Some(player_b)) = (player_a, player_b) {
+                if player_a == player_b {
+                    continue;
+                }
                 collisions.push(player_a);
                 collisions.push(player_b);
             }
@@ -140,22 +213,33 @@ impl Board {
             .collect_vec();
 
         let mut current_position = marker.position;
+        let mut visited = vec![];
         // follow path until it ends on a tile other than the one we would place
         // because find_path_endpoint does not see this tile and thinks the path ends here
-        loop {
+        let end = loop {
+            if visited.contains(&current_position) {
+                // a closed loop, the path never reaches the edge
+                break None;
+            }
+            visited.push(current_position);
             let entry_point =
                 current_position.entry_point_index_on(tile_pos).unwrap();
             let exit_point = tile.connections[entry_point];
             current_position = self.find_path_endpoint(tile_pos, exit_point);
             if !nonterminal_positions.contains(&current_position) {
-                break;
+                break Some(current_position);
             }
-        }
+        };
 
-        let end = current_position;
         let colliders = self.find_collisions(tile, tile_pos);
 
-        end.is_edge() || colliders.contains(&active_player)
+        end.is_some_and(|end| end.is_edge()) || colliders.contains(&active_player)
+    }
+
+    /// reverts place_tile, patch has to be recorded right before placing the tile
+    pub(crate) fn remove_tile(&mut self, position: Coord, patch: GraphPatch) {
+        self.tiles[position.0][position.1] = None;
+        self.graph.apply_patch(patch);
     }
 
     pub fn eliminate_player(&mut self, player: usize) {
@@ -188,7 +272,299 @@ impl Board {
     }
 }
 
+#[pymethods]
+impl Board {
+    /// the board turned by a quarter, in the direction Tile.rotated(1) turns a tile:
+    /// the south side becomes the east side
+    pub fn rotate90(&self) -> Board {
+        let last = LATTICE_SIZE - 1;
+        self.transformed(
+            |(x, y)| (y, BOARD_SIZE - 1 - x),
+            |(x, y)| (y, last - x),
+            |tile| tile.rotated(1),
+        )
+    }
+
+    /// the board reflected across the vertical axis like Tile.mirror,
+    /// the east and west sides swap
+    pub fn reflect(&self) -> Board {
+        let last = LATTICE_SIZE - 1;
+        self.transformed(
+            |(x, y)| (BOARD_SIZE - 1 - x, y),
+            |(x, y)| (last - x, y),
+            |tile| tile.mirror(),
+        )
+    }
+
+    /// build a board from placed tiles and the starting edge index of each marker
+    /// markers are then moved along the placed tiles,
+    /// players whose path leads back to the edge are eliminated
+    #[staticmethod]
+    pub fn from_placements(
+        placements: Vec<(Coord, Tile)>,
+        markers: Vec<usize>,
+    ) -> PyResult<Board> {
+        let mut board = Board::new();
+        for ((x, y), tile) in placements {
+            if x >= BOARD_SIZE || y >= BOARD_SIZE {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    format!("tile position ({x}, {y}) is not on the board"),
+                ));
+            }
+            if board.tiles[x][y].is_some() {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    format!("more than one tile placed at ({x}, {y})"),
+                ));
+            }
+            if tile.index().is_none() {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    format!(
+                        "tile {tile:?} at ({x}, {y}) is not a rotation of ALL_TILES"
+                    ),
+                ));
+            }
+            board.graph.place_tile(tile, (x, y));
+            board.tiles[x][y] = Some(tile);
+        }
+        for position in markers {
+            if position >= NUM_EDGE_POSITIONS {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    format!(
+                        "edge index {} is out of range 0..{}",
+                        position, NUM_EDGE_POSITIONS
+                    ),
+                ));
+            }
+            if !board.place_marker(position) {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    format!("more than one marker placed at edge index {position}"),
+                ));
+            }
+        }
+        for player in board.move_markers() {
+            board.eliminate_player(player);
+        }
+        Ok(board)
+    }
+
+    /// wether place_tile can be called for the player: the player has a marker
+    /// on the board and the tile in front of it is on the board and empty
+    pub fn can_place_tile(&self, player: usize) -> bool {
+        let Some(marker) = self.marker_of(player) else {
+            return false;
+        };
+        let adjacents = marker.position.adjacent_tiles();
+        let target = match marker.previous_tile {
+            Some(previous) => adjacents.iter().find(|coord| **coord != previous),
+            None => adjacents.first(),
+        };
+        target.is_some_and(|&(x, y)| {
+            x < BOARD_SIZE && y < BOARD_SIZE && self.tiles[x][y].is_none()
+        })
+    }
+
+    /// wether a marker placed at the edge position would face the same tile as
+    /// a marker already on the board, the tile placed there could then join
+    /// their paths and eliminate both, place_marker still allows this
+    /// false for indices outside 0..NUM_EDGE_POSITIONS
+    pub fn marker_would_collide_at(&self, position_index: usize) -> bool {
+        if position_index >= NUM_EDGE_POSITIONS {
+            return false;
+        }
+        let tile = MarkerPosition::from_index(position_index).adjacent_tiles()[0];
+        (0..self.markers.len()).any(|player| {
+            self.markers[player].is_some()
+                && self.next_tile_of_player(player) == tile
+        })
+    }
+
+    /// number of markers that have not been eliminated
+    pub fn num_survivors(&self) -> usize {
+        self.markers
+            .iter()
+            .filter(|marker| marker.is_some())
+            .count()
+    }
+
+    /// player whose marker is on the graph node, None if there is no marker on it
+    /// nodes removed from the graph are never occupied, a path goes through them
+    /// so markers can not stop there
+    pub fn player_at(&self, node_id: usize) -> Option<usize> {
+        self.graph.vertices.get(node_id)?.as_ref()?;
+        self.markers.iter().position(|marker| {
+            marker.is_some_and(|marker| marker.position.node_id() == node_id)
+        })
+    }
+
+    /// one-hot encoding of the placed tiles with shape [BOARD_SIZE, BOARD_SIZE, 36]
+    /// channel c at (x, y) is 1 if the tile there is a rotation of ALL_TILES[c]
+    /// channel 35 is 1 if there is no tile at (x, y)
+    pub fn tile_tensor(&self) -> PyTensor {
+        let size = BOARD_SIZE as i64;
+        PyTensor(Tensor::from_slice(&self.tile_one_hot()).view([size, size, 36]))
+    }
+
+    /// number of tiles the player's path crosses from the edge it started on
+    /// to the current position of the marker, 0 for eliminated players
+    pub fn path_length(&self, player: usize) -> usize {
+        let Some(Some(marker)) = self.markers.get(player) else {
+            return 0;
+        };
+        let Some(previous) = marker.previous_tile else {
+            return 0; // has not moved yet
+        };
+        let tile = self.tiles[previous.0][previous.1]
+            .expect("the marker should have arrived from a placed tile");
+        let entry_idx = marker.position.entry_point_index_on(previous).unwrap();
+        // the path ends at the marker, so following it backwards leads to the start
+        let (_start, num_tiles) =
+            self.follow_path(previous, tile.connections[entry_idx]);
+        num_tiles + 1
+    }
+
+    /// the edge positions place_marker still accepts, in edge index order
+    pub fn available_marker_positions(&self) -> Vec<MarkerPosition> {
+        (0..NUM_EDGE_POSITIONS)
+            .map(MarkerPosition::from_index)
+            .filter(|position| !self.position_is_taken(*position))
+            .collect()
+    }
+
+    /// number of graph nodes the player's path can still reach, a path can be
+    /// extended along any edge that is not built yet, see BoardGraph.reachable_count
+    /// counted from the end of the path, where the next placed tile extends it
+    /// 0 for players without a marker
+    pub fn territory(&self, player: usize) -> usize {
+        if self.marker_of(player).is_none() {
+            return 0;
+        }
+        let (path_end, _) = self.find_player_path_end(player);
+        self.graph.reachable_count(path_end.node_id())
+    }
+
+    /// positions the player's marker passes through following the placed tiles,
+    /// one for each tile side it crosses, from where it is now to where the
+    /// path ends, which is on the board edge if the player is eliminated
+    /// empty if the player has no marker
+    pub fn path_trace(&self, player: usize) -> Vec<MarkerPosition> {
+        let Some(Some(marker)) = self.markers.get(player) else {
+            return vec![];
+        };
+        let mut trace = vec![];
+        let (coords, exit_idx) = match marker.previous_tile {
+            Some(previous) => (
+                previous,
+                marker.position.entry_point_index_on(previous).unwrap(),
+            ),
+            None => {
+                trace.push(marker.position);
+                let (x, y) = marker.position.adjacent_tiles()[0];
+                let Some(tile) = self.tiles[x][y] else {
+                    return trace;
+                };
+                let entry_idx =
+                    marker.position.entry_point_index_on((x, y)).unwrap();
+                ((x, y), tile.connections[entry_idx])
+            }
+        };
+        self.walk_path(coords, exit_idx, |position| trace.push(position));
+        trace
+    }
+
+    /// the position the player's marker ends up at following the placed tiles
+    /// this is an edge position if the path leads off the board
+    pub fn player_path_end(&self, player: usize) -> PyResult<MarkerPosition> {
+        self.require_marker(player)?;
+        Ok(self.find_player_path_end(player).0)
+    }
+
+    /// wether placing the tile in front of the player eliminates them,
+    /// either by leading off the board or into a collision
+    #[pyo3(name = "move_is_suicide")]
+    fn py_move_is_suicide(&self, tile: Tile, player: usize) -> PyResult<bool> {
+        self.require_marker(player)?;
+        Ok(self.move_is_suicide(tile, player))
+    }
+
+    /// minimum number of empty tiles the player's path has to cross
+    /// to lead off the board, following the built paths it runs into
+    /// 0 if the player has been eliminated or has not placed a marker,
+    /// or if the placed tiles already lead them off the board, which happens
+    /// when the markers have not been moved after the last placement
+    /// raises ValueError if the edge can not be reached, only possible on
+    /// an inconsistent board
+    pub fn edge_distance(&self, player: usize) -> PyResult<usize> {
+        if self.marker_of(player).is_none() {
+            return Ok(0);
+        }
+        let (path_end, is_different) = self.find_player_path_end(player);
+        if is_different && path_end.is_edge() {
+            return Ok(0);
+        }
+        let start = path_end.node_id();
+        // 0-1 bfs, following a built path is free, crossing an empty tile costs 1
+        let mut dist = [usize::MAX; NUM_NODES];
+        let mut queue = VecDeque::from([start]);
+        dist[start] = 0;
+        while let Some(current_id) = queue.pop_front() {
+            if current_id != start
+                && self.graph.vertices[current_id].is_some_and(|pos| pos.is_edge())
+            {
+                return Ok(dist[current_id]);
+            }
+            for (to_id, built) in &self.graph.adjacency_list[current_id] {
+                if *built && current_id == start {
+                    continue; // the player's own path leads back where it came from
+                }
+                let to_dist = dist[current_id] + usize::from(!built);
+                if to_dist >= dist[*to_id] {
+                    continue;
+                }
+                dist[*to_id] = to_dist;
+                if *built {
+                    queue.push_front(*to_id);
+                } else {
+                    queue.push_back(*to_id);
+                }
+            }
+        }
+        Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "the path of player {player} can not reach the board edge"
+        )))
+    }
+
+    /// marker positions on the lattice,
+    /// shape [num_players, LATTICE_SIZE, LATTICE_SIZE]
+    /// channel p is 1 at the lattice coordinates of player p's marker,
+    /// all zeros if the player is eliminated or has not placed a marker yet
+    pub fn marker_tensor(&self, num_players: usize) -> PyTensor {
+        let mut one_hot = vec![0i8; num_players * LATTICE_SIZE * LATTICE_SIZE];
+        for (player, marker) in self.markers.iter().enumerate().take(num_players) {
+            if let Some(marker) = marker {
+                let (x, y) = marker.position.coords;
+                one_hot[(player * LATTICE_SIZE + x) * LATTICE_SIZE + y] = 1;
+            }
+        }
+        let size = LATTICE_SIZE as i64;
+        PyTensor(Tensor::from_slice(&one_hot).view([num_players as i64, size, size]))
+    }
+}
+
 impl Board {
+    /// the player's marker, None if they have been eliminated or have not placed one
+    pub(crate) fn marker_of(&self, player: usize) -> Option<Marker> {
+        *self.markers.get(player)?
+    }
+
+    /// marker_of for python methods that need a marker, raises ValueError
+    pub(crate) fn require_marker(&self, player: usize) -> PyResult<Marker> {
+        self.marker_of(player).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "player {player} has been eliminated or has not placed a marker"
+            ))
+        })
+    }
+
     /// end of the path the player is on, and wether it is different from the current position
     fn find_player_path_end(&self, player: usize) -> (MarkerPosition, bool) {
         let marker = self.markers[player]
@@ -225,9 +601,28 @@ impl Board {
         location: Coord,
         exit: usize,
     ) -> MarkerPosition {
+        self.follow_path(location, exit).0
+    }
+
+    /// same as find_path_endpoint, also returns the number of tiles crossed
+    /// (not counting the one at location)
+    fn follow_path(&self, location: Coord, exit: usize) -> (MarkerPosition, usize) {
+        self.walk_path(location, exit, |_position| {})
+    }
+
+    /// follow_path that calls visit with every position on the way, starting
+    /// with the one at exit and ending with the returned one
+    fn walk_path(
+        &self,
+        location: Coord,
+        exit: usize,
+        mut visit: impl FnMut(MarkerPosition),
+    ) -> (MarkerPosition, usize) {
         let mut current_position =
             MarkerPosition::from_entry_point_index(location, exit);
+        visit(current_position);
         let mut last_tile_coord = location;
+        let mut num_tiles = 0;
         loop {
             let adjacents = current_position.adjacent_tiles();
             let next_tile_coord =
@@ -252,12 +647,15 @@ impl Board {
             last_tile_coord = next_tile_coord;
             current_position =
                 MarkerPosition::from_entry_point_index(next_tile_coord, exit_index);
+            visit(current_position);
+            num_tiles += 1;
         }
-        current_position
+        (current_position, num_tiles)
     }
 }
 
 #[test]
+#[cfg(not(feature = "board-5x5"))] // coordinates on the 6x6 board
 fn test_path_end_of() {
     let mut board = Board::new();
     board.tiles[4][1] = Some(Tile {
@@ -278,21 +676,25 @@ fn test_path_end_of() {
 }
 
 #[test]
+#[cfg(not(feature = "board-5x5"))] // coordinates on the 6x6 board
 fn test_move_markers() {
     let mut board = Board::new();
     board.place_marker(28);
+    assert_eq!(board.path_length(0), 0);
     board.tiles[3][0] = Some(find_tile_with_connection(4, 1));
     board.move_markers();
     assert_eq!(
         board.markers[0].unwrap().position,
         MarkerPosition::from_lattice_coordinates((11, 3))
     );
+    assert_eq!(board.path_length(0), 1);
     board.tiles[3][1] = Some(find_tile_with_connection(4, 2));
     board.move_markers();
     assert_eq!(
         board.markers[0].unwrap().position,
         MarkerPosition::from_lattice_coordinates((12, 5))
     );
+    assert_eq!(board.path_length(0), 2);
     board.tiles[4][1] = Some(find_tile_with_connection(7, 2));
     board.tiles[5][1] = Some(find_tile_with_connection(7, 5));
     board.move_markers();
@@ -300,7 +702,399 @@ fn test_move_markers() {
         board.markers[0].unwrap().position,
         MarkerPosition::from_lattice_coordinates((16, 3))
     );
+    assert_eq!(board.path_length(0), 4);
     board.tiles[5][0] = Some(find_tile_with_connection(0, 6));
     board.tiles[4][0] = Some(find_tile_with_connection(3, 4));
+    let end = board.player_path_end(0).unwrap();
+    assert!(end.is_edge());
     assert!(board.move_markers() == vec![0]);
+    board.eliminate_player(0);
+    assert!(board.player_path_end(0).is_err());
+    assert!(board.player_path_end(1).is_err());
+}
+
+#[test]
+fn test_tile_tensor() {
+    let mut board = Board::new();
+    board.tiles[2][4] = Some(ALL_TILES[7].rotated(1));
+    let tensor = board.tile_tensor();
+    let size = BOARD_SIZE as i64;
+    assert_eq!(tensor.size(), [size, size, 36]);
+    for (x, y) in (0..size).cartesian_product(0..size) {
+        let channels = (0..36)
+            .filter(|c| tensor.int64_value(&[x, y, *c]) == 1)
+            .collect_vec();
+        let expected = if (x, y) == (2, 4) { 7 } else { 35 };
+        assert_eq!(channels, [expected]);
+    }
+}
+
+#[test]
+fn test_marker_tensor() {
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_marker(5);
+    board.place_marker(9);
+    board.eliminate_player(1);
+    let tensor = board.marker_tensor(4);
+    let size = LATTICE_SIZE as i64;
+    assert_eq!(tensor.size(), [4, size, size]);
+    assert_eq!(tensor.sum(tch::Kind::Int64).int64_value(&[]), 2);
+    let (x, y) = MarkerPosition::from_index(9).coords;
+    assert_eq!(tensor.int64_value(&[2, x as i64, y as i64]), 1);
+}
+
+#[test]
+fn test_from_placements() {
+    let mut played = Board::new();
+    played.place_marker(0);
+    played.place_marker(20);
+    let tile = find_tile_with_connection(0, 2);
+    let tile_pos = played.next_tile_of_player(0);
+    played.place_tile(tile, 0);
+    played.move_markers();
+
+    let board = Board::from_placements(vec![(tile_pos, tile)], vec![0, 20]).unwrap();
+    assert_eq!(board, played);
+
+    assert!(Board::from_placements(vec![((6, 0), tile)], vec![]).is_err());
+    assert!(
+        Board::from_placements(vec![((1, 1), tile), ((1, 1), tile)], vec![])
+            .is_err()
+    );
+    assert!(Board::from_placements(vec![], vec![48]).is_err());
+    assert!(Board::from_placements(vec![], vec![3, 3]).is_err());
+    // what Tile() creates from python
+    let invalid = Tile::default();
+    assert!(Board::from_placements(vec![((1, 1), invalid)], vec![]).is_err());
+}
+
+#[test]
+fn test_num_survivors() {
+    let mut board = Board::new();
+    assert_eq!(board.num_survivors(), 0);
+    for position in [0, 5, 9] {
+        board.place_marker(position);
+    }
+    assert_eq!(board.num_survivors(), 3);
+    board.eliminate_player(1);
+    assert_eq!(board.num_survivors(), 2);
+}
+
+#[test]
+#[cfg(not(feature = "board-5x5"))] // coordinates on the 6x6 board
+fn test_edge_distance() {
+    let mut board = Board::new();
+    board.place_marker(2);
+    board.place_marker(30);
+    // on the edge, the next tile can lead straight back off the board
+    assert_eq!(board.edge_distance(0).unwrap(), 1);
+
+    // straight up into tile (1, 4), which is one away from the edge,
+    // the tile below has a path from (1, 4) to the bottom edge
+    let mut straight = board.clone();
+    straight.place_tile(Tile::from_code("16-25-38-47").unwrap(), 0);
+    straight.move_markers();
+    assert_eq!(straight.next_tile_of_player(0), (1, 4));
+    assert_eq!(straight.edge_distance(0).unwrap(), 1);
+
+    // same position, but none of the paths below lead to the edge
+    board.place_tile(Tile::from_code("16-23-47-58").unwrap(), 0);
+    board.move_markers();
+    assert_eq!(board.next_tile_of_player(0), (1, 4));
+    assert_eq!(board.edge_distance(0).unwrap(), 2);
+
+    board.eliminate_player(1);
+    assert_eq!(board.edge_distance(1).unwrap(), 0);
+    assert_eq!(board.edge_distance(7).unwrap(), 0);
+
+    // a u turn in front of a marker that has not moved leads it off the board
+    let mut board = Board::new();
+    board.place_marker(0);
+    let u_turn = Tile::from_code("12-34-56-78").unwrap();
+    board.tiles[0][BOARD_SIZE - 1] = Some(u_turn);
+    board.graph.place_tile(u_turn, (0, BOARD_SIZE - 1));
+    assert_eq!(board.edge_distance(0).unwrap(), 0);
+}
+
+#[test]
+fn test_py_move_is_suicide() {
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_marker(20);
+    let u_turn = Tile::from_code("12-34-56-78").unwrap();
+    let straight = Tile::from_code("16-25-38-47").unwrap();
+    assert!(board.py_move_is_suicide(u_turn, 0).unwrap());
+    assert!(!board.py_move_is_suicide(straight, 0).unwrap());
+    board.eliminate_player(1);
+    assert!(board.py_move_is_suicide(straight, 1).is_err());
+    assert!(board.py_move_is_suicide(straight, 2).is_err());
+}
+
+#[test]
+fn test_board_eq_ignores_placement_order() {
+    let tiles = [
+        Tile::from_code("12-37-48-56").unwrap(),
+        Tile::from_code("12-34-56-78").unwrap(),
+        Tile::from_code("16-23-47-58").unwrap(),
+    ];
+    let placements = std::iter::zip([(1, 1), (1, 2), (2, 1)], tiles).collect_vec();
+    let board = Board::from_placements(placements.clone(), vec![]).unwrap();
+    for order in placements.iter().copied().permutations(placements.len()) {
+        assert_eq!(Board::from_placements(order, vec![]).unwrap(), board);
+    }
+}
+
+#[test]
+fn test_player_at() {
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_marker(20);
+    let first = MarkerPosition::from_index(0).node_id();
+    let second = MarkerPosition::from_index(20).node_id();
+    assert_eq!(board.player_at(first), Some(0));
+    assert_eq!(board.player_at(second), Some(1));
+    assert_eq!(
+        board.player_at(MarkerPosition::from_index(1).node_id()),
+        None
+    );
+    assert_eq!(board.player_at(NUM_NODES), None);
+
+    let tile = Tile::from_code("16-25-38-47").unwrap();
+    for _ in 0..2 {
+        board.place_tile(tile, 0);
+        board.move_markers();
+    }
+    let moved = board.markers[0].unwrap().position.node_id();
+    assert_eq!(board.player_at(moved), Some(0));
+    assert_eq!(board.player_at(first), None);
+    // the marker passed through the node between the two tiles
+    let removed = (0..NUM_NODES)
+        .filter(|id| board.graph.vertices[*id].is_none())
+        .collect_vec();
+    assert!(!removed.is_empty());
+    assert!(removed.iter().all(|id| board.player_at(*id).is_none()));
+    board.eliminate_player(1);
+    assert_eq!(board.player_at(second), None);
+}
+
+#[test]
+fn test_move_is_suicide_terminates_on_loop() {
+    // not reachable in a real game: the marker sits between two u-turns,
+    // so the path leaves the new tile and comes straight back to the marker
+    let u_turn = Tile::from_code("12-34-56-78").unwrap();
+    let mut board = Board::new();
+    board.tiles[0][1] = Some(u_turn);
+    board.markers.push(Some(Marker {
+        position: MarkerPosition::from_entry_point_index((1, 1), 6),
+        previous_tile: Some((0, 1)),
+        has_moved: true,
+    }));
+    assert_eq!(board.next_tile_of_player(0), (1, 1));
+    assert!(!board.move_is_suicide(u_turn, 0));
+}
+
+#[test]
+fn test_can_place_tile() {
+    let mut board = Board::new();
+    assert!(!board.can_place_tile(0));
+    board.place_marker(0);
+    board.place_marker(20);
+    assert!(board.can_place_tile(0));
+    assert!(!board.can_place_tile(2));
+
+    let (x, y) = board.next_tile_of_player(1);
+    board.tiles[x][y] = Some(Tile::from_code("12-34-56-78").unwrap());
+    assert!(!board.can_place_tile(1));
+
+    board.place_tile(Tile::from_code("16-25-38-47").unwrap(), 0);
+    board.move_markers();
+    assert!(board.can_place_tile(0));
+    board.eliminate_player(0);
+    assert!(!board.can_place_tile(0));
+    assert!(board.require_marker(0).is_err());
+    assert_eq!(board.require_marker(1).ok(), board.markers[1]);
+}
+
+#[test]
+fn test_find_collisions_corner_start() {
+    // the first and last edge positions are on the same corner tile
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_marker(NUM_EDGE_POSITIONS - 1);
+    let tile_pos = board.next_tile_of_player(0);
+    assert_eq!(board.next_tile_of_player(1), tile_pos);
+    let last = MarkerPosition::from_index(NUM_EDGE_POSITIONS - 1);
+    assert_eq!(last.entry_point_index_on(tile_pos), Some(7));
+
+    let tile = find_tile_with_connection(0, 7);
+    assert_eq!(board.find_collisions(tile, tile_pos), vec![0, 1]);
+    let tile = find_tile_with_connection(0, 1);
+    assert!(board.find_collisions(tile, tile_pos).is_empty());
+}
+
+#[test]
+fn test_marker_would_collide_at() {
+    let mut board = Board::new();
+    assert!(!board.marker_would_collide_at(0));
+    board.place_marker(0);
+    // the same position and the other position on the same tile side
+    assert!(board.marker_would_collide_at(0));
+    assert!(board.marker_would_collide_at(1));
+    // the corner tile also borders the last edge position
+    assert!(board.marker_would_collide_at(NUM_EDGE_POSITIONS - 1));
+    assert!(!board.marker_would_collide_at(2));
+    assert!(!board.marker_would_collide_at(NUM_EDGE_POSITIONS));
+
+    board.eliminate_player(0);
+    assert!(!board.marker_would_collide_at(1));
+}
+
+#[test]
+fn test_path_trace() {
+    let mut board = Board::new();
+    assert!(board.path_trace(0).is_empty());
+    board.place_marker(0);
+    let start = MarkerPosition::from_index(0);
+    assert_eq!(board.path_trace(0), [start]);
+
+    let straight = Tile::from_code("16-25-38-47").unwrap();
+    board.place_tile(straight, 0);
+    board.tiles[0][BOARD_SIZE - 2] = Some(straight);
+    let trace = board.path_trace(0);
+    assert_eq!(trace.len(), 3);
+    assert_eq!(trace[0], start);
+    assert_eq!(trace[2].coords, (start.coords.0, start.coords.1 - 6));
+    assert_eq!(*trace.last().unwrap(), board.find_player_path_end(0).0);
+
+    board.move_markers();
+    assert_eq!(board.path_trace(0), trace[2..]);
+
+    // the u turn leads back to the edge
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_tile(Tile::from_code("12-34-56-78").unwrap(), 0);
+    let trace = board.path_trace(0);
+    assert_eq!(trace, [start, MarkerPosition::from_index(1)]);
+    assert!(trace[1].is_edge());
+}
+
+#[test]
+fn test_rotate90() {
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_marker(20);
+    board.place_tile(Tile::from_code("16-25-38-47").unwrap(), 0);
+    board.place_tile(find_tile_with_connection(4, 2), 1);
+    board.move_markers();
+    let rotated = board.rotate90();
+    assert_eq!(
+        rotated.tiles[BOARD_SIZE - 1][BOARD_SIZE - 1],
+        board.tiles[0][BOARD_SIZE - 1].map(|t| t.rotated(1))
+    );
+    // index 0 is on the south side of the bottom left tile,
+    // it moves to the east side of the bottom right one
+    let mut fresh = Board::new();
+    fresh.place_marker(0);
+    let fresh = fresh.rotate90();
+    let (x, y) = MarkerPosition::from_index(0).coords;
+    let last = LATTICE_SIZE - 1;
+    assert_eq!(fresh.markers[0].unwrap().position.coords, (y, last - x));
+    let corner = (BOARD_SIZE - 1, BOARD_SIZE - 1);
+    assert_eq!(fresh.next_tile_of_player(0), corner);
+
+    assert_eq!(rotated.graph.validate(), Ok(()));
+    for player in 0..2 {
+        assert_eq!(rotated.path_length(player), board.path_length(player));
+        let next = board.next_tile_of_player(player);
+        assert_eq!(
+            rotated.next_tile_of_player(player),
+            (next.1, BOARD_SIZE - 1 - next.0)
+        );
+    }
+}
+
+#[test]
+fn test_symmetry_transforms_are_consistent() {
+    let mut env = TsuroEnv::new(Some(3), Some(4), EnvConfig::default()).unwrap();
+    for position in [0, 17, 30] {
+        env.step_place_marker(position).unwrap();
+    }
+    for _ in 0..6 {
+        let tile = env.legal_actions()[0];
+        env.step_place_tile(tile).unwrap();
+    }
+    let board = env.board.clone();
+    assert_eq!(board.rotate90().rotate90().rotate90().rotate90(), board);
+    assert_ne!(board.rotate90(), board);
+    assert_eq!(board.reflect().reflect(), board);
+    assert_ne!(board.reflect(), board);
+    // a reflection reverses the direction of a turn
+    assert_eq!(board.rotate90().reflect().rotate90().reflect(), board);
+    for transformed in [board.rotate90(), board.reflect()] {
+        assert_eq!(transformed.graph.validate(), Ok(()));
+        assert_eq!(transformed.num_survivors(), board.num_survivors());
+        for player in 0..3 {
+            assert_eq!(transformed.path_length(player), board.path_length(player));
+        }
+    }
+}
+
+#[test]
+fn test_territory() {
+    let mut board = Board::new();
+    assert_eq!(board.territory(0), 0);
+    board.place_marker(0);
+    assert_eq!(board.territory(0), NUM_NODES);
+
+    // walling off the corner tile with u turns leaves the marker in a pocket
+    let u_turn = Tile::from_code("12-34-56-78").unwrap();
+    board.tiles[1][BOARD_SIZE - 1] = Some(u_turn);
+    board.graph.place_tile(u_turn, (1, BOARD_SIZE - 1));
+    board.tiles[0][BOARD_SIZE - 2] = Some(u_turn);
+    board.graph.place_tile(u_turn, (0, BOARD_SIZE - 2));
+    let territory = board.territory(0);
+    assert_eq!(territory, 8); // the nodes around the corner tile
+    let node = MarkerPosition::from_index(0).node_id();
+    assert_eq!(territory, board.graph.reachable_count(node));
+    assert_eq!(board.graph.reachable_count(NUM_NODES), 0);
+
+    board.eliminate_player(0);
+    assert_eq!(board.territory(0), 0);
+
+    // a tile placed in front of a marker that has not moved onto it yet
+    // removes the marker's node, the path continues on the far side of the tile
+    let mut board = Board::new();
+    board.place_marker(0);
+    let straight = Tile::from_code("16-25-38-47").unwrap();
+    board.place_tile(straight, 0);
+    board.move_markers();
+    let tile_pos = board.next_tile_of_player(0);
+    board.tiles[tile_pos.0][tile_pos.1] = Some(straight);
+    board.graph.place_tile(straight, tile_pos);
+    let marker_node = board.markers[0].unwrap().position.node_id();
+    let path_end = board.player_path_end(0).unwrap().node_id();
+    assert_ne!(marker_node, path_end);
+    assert_eq!(board.graph.reachable_count(marker_node), 0);
+    assert!(board.territory(0) > 0);
+    assert_eq!(board.territory(0), board.graph.reachable_count(path_end));
+}
+
+#[test]
+fn test_available_marker_positions() {
+    let mut board = Board::new();
+    let all = board.available_marker_positions();
+    assert_eq!(all.len(), NUM_EDGE_POSITIONS);
+    assert!(all.iter().all(MarkerPosition::is_edge));
+    board.place_marker(3);
+    board.place_marker(20);
+    let available = board.available_marker_positions();
+    assert_eq!(available.len(), NUM_EDGE_POSITIONS - 2);
+    assert!(!available.contains(&MarkerPosition::from_index(3)));
+    assert!(!available.contains(&MarkerPosition::from_index(20)));
+    assert!(available.contains(&MarkerPosition::from_index(4)));
+    for position in available {
+        let index = all.iter().position(|p| *p == position).unwrap();
+        assert!(board.clone().place_marker(index));
+    }
 }
@@ -1,7 +1,9 @@
 use itertools::Itertools;
 use pyo3_tch::*;
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
 use std::iter::zip;
-use tch::{Kind, Tensor};
+use tch::Tensor;
 
 use crate::*;
 
@@ -12,25 +14,47 @@ impl MarkerPosition {
         if x % 3 == 0 {
             x /= 3;
             y = y - 1 - (y / 3);
-            return x * 12 + y;
+            return x * 2 * BOARD_SIZE + y;
         } else {
             y /= 3;
             x = x - 1 - (x / 3);
-            return (6 * 12 + 12) + y * 12 + x;
+            return (BOARD_SIZE + 1) * 2 * BOARD_SIZE + y * 2 * BOARD_SIZE + x;
         }
     }
+
+    /// inverse of node_id
+    #[staticmethod]
+    pub fn from_node_id(id: usize) -> PyResult<Self> {
+        if id >= NUM_NODES {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "node id {id} is out of range 0..{NUM_NODES}"
+            )));
+        }
+        // there are 2 positions on each tile side, they are not on multiples of 3
+        let along_line = |idx: usize| idx / 2 * 3 + idx % 2 + 1;
+        let num_vertical = (BOARD_SIZE + 1) * 2 * BOARD_SIZE;
+        let coords = if id < num_vertical {
+            let (line, idx) = (id / (2 * BOARD_SIZE), id % (2 * BOARD_SIZE));
+            (line * 3, along_line(idx))
+        } else {
+            let id = id - num_vertical;
+            let (line, idx) = (id / (2 * BOARD_SIZE), id % (2 * BOARD_SIZE));
+            (along_line(idx), line * 3)
+        };
+        Ok(Self::from_lattice_coordinates(coords))
+    }
 }
 
 impl BoardGraph {
     /// initialize graph for an empty board
     pub(super) fn new() -> Self {
         let mut ret = BoardGraph {
-            adjacency_list: [const { vec![] }; 168],
-            vertices: [None; 168],
+            adjacency_list: [const { vec![] }; NUM_NODES],
+            vertices: [None; NUM_NODES],
         };
 
         // all valid positions are nodes of the graph
-        for (x, y) in (0..19).cartesian_product(0..19) {
+        for (x, y) in (0..LATTICE_SIZE).cartesian_product(0..LATTICE_SIZE) {
             if (x % 3 == 0) ^ (y % 3 == 0) {
                 let markerpos = MarkerPosition::from_lattice_coordinates((x, y));
                 let idx = markerpos.node_id();
@@ -117,24 +141,236 @@ impl BoardGraph {
             self.adjacency_list[path_ends[0]].push((path_ends[1], true));
             self.adjacency_list[path_ends[1]].push((path_ends[0], true));
         }
+        debug_assert_eq!(self.validate(), Ok(()));
+    }
+
+    /// check that every edge is listed on both of its nodes with the same
+    /// built flag and that removed nodes have no edges
+    pub fn validate(&self) -> Result<(), String> {
+        for (id, neighbours) in self.adjacency_list.iter().enumerate() {
+            if self.vertices[id].is_none() && !neighbours.is_empty() {
+                return Err(format!("removed node {id} has edges {neighbours:?}"));
+            }
+            for &(to_id, built) in neighbours {
+                let Some(back) = self.adjacency_list.get(to_id) else {
+                    return Err(format!(
+                        "node {id} has an edge to invalid id {to_id}"
+                    ));
+                };
+                if !back.contains(&(id, built)) {
+                    return Err(format!(
+                        "edge {id} -> {to_id} (built: {built}) has no matching edge back"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BoardGraph {
+    /// vertex and sorted adjacency list of every node, in a form that does not
+    /// depend on the order the tiles were placed in
+    /// a closed loop is left on whichever of its nodes was merged last,
+    /// so nodes only connected to themselves are treated as removed
+    fn normalized_nodes(
+        &self,
+    ) -> impl Iterator<Item = (Option<MarkerPosition>, Vec<(usize, bool)>)> + '_
+    {
+        zip(&self.vertices, &self.adjacency_list).enumerate().map(
+            |(id, (vertex, neighbours))| {
+                if !neighbours.is_empty()
+                    && neighbours.iter().all(|(to_id, _built)| *to_id == id)
+                {
+                    return (None, vec![]);
+                }
+                (
+                    *vertex,
+                    neighbours.iter().copied().sorted_unstable().collect(),
+                )
+            },
+        )
     }
 }
 
-#[pymethods]
 impl BoardGraph {
-    pub fn adjacency_matrix_tensor<'py>(&self) -> PyTensor {
-        let mut adjacency_matrix = vec![vec![0i8; 168]; 168];
+    /// write the adjacency_matrix_tensor entries into a NUM_NODES * NUM_NODES slice
+    /// that is all zeros
+    pub(crate) fn fill_adjacency_matrix(&self, matrix: &mut [i8]) {
         for (from_id, neighbors) in self.adjacency_list.iter().enumerate() {
             for &(to_id, built) in neighbors {
-                adjacency_matrix[from_id][to_id] = if built { 1 } else { -1 };
+                matrix[from_id * NUM_NODES + to_id] = if built { 1 } else { -1 };
             }
         }
+    }
+}
+
+impl PartialEq for BoardGraph {
+    fn eq(&self, other: &Self) -> bool {
+        self.normalized_nodes().eq(other.normalized_nodes())
+    }
+}
+
+impl Eq for BoardGraph {}
+
+impl Hash for BoardGraph {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for node in self.normalized_nodes() {
+            node.hash(state);
+        }
+    }
+}
+
+/// state of the nodes a tile placement modifies, applying it reverts the placement
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct GraphPatch {
+    node_ids: Vec<usize>,
+    vertices: Vec<Option<MarkerPosition>>,
+    adjacency_lists: Vec<Vec<(usize, bool)>>,
+}
 
-        let adjacency_matrix_tensor = Tensor::from_slice(&adjacency_matrix.concat())
-            .view([168, 168])
-            .to_kind(Kind::Int8);
+impl BoardGraph {
+    /// record the nodes that place_tile at tile_coords can modify:
+    /// the ones on the tile and the ones they are connected to
+    pub(crate) fn patch_for(&self, tile_coords: Coord) -> GraphPatch {
+        let tile_nodes = (0..8).map(|entry_idx| {
+            MarkerPosition::from_entry_point_index(tile_coords, entry_idx).node_id()
+        });
+        let node_ids = tile_nodes
+            .flat_map(|id| {
+                let neighbours =
+                    self.adjacency_list[id].iter().map(|(to_id, _)| *to_id);
+                std::iter::once(id).chain(neighbours)
+            })
+            .sorted_unstable()
+            .dedup()
+            .collect_vec();
+        GraphPatch {
+            vertices: node_ids.iter().map(|id| self.vertices[*id]).collect(),
+            adjacency_lists: node_ids
+                .iter()
+                .map(|id| self.adjacency_list[*id].clone())
+                .collect(),
+            node_ids,
+        }
+    }
 
-        PyTensor(adjacency_matrix_tensor)
+    pub(crate) fn apply_patch(&mut self, patch: GraphPatch) {
+        let nodes = zip(patch.vertices, patch.adjacency_lists);
+        for (id, (vertex, adjacency)) in zip(patch.node_ids, nodes) {
+            self.vertices[id] = vertex;
+            self.adjacency_list[id] = adjacency;
+        }
+    }
+}
+
+#[pymethods]
+impl BoardGraph {
+    /// [NUM_NODES, NUM_NODES] int8 tensor, 1 for built edges,
+    /// -1 for edges that are not built yet and 0 if there is no edge
+    pub fn adjacency_matrix_tensor(&self) -> PyTensor {
+        let mut adjacency_matrix = vec![0i8; NUM_NODES * NUM_NODES];
+        self.fill_adjacency_matrix(&mut adjacency_matrix);
+        let size = NUM_NODES as i64;
+        PyTensor(Tensor::from_slice(&adjacency_matrix).view([size, size]))
+    }
+
+    /// Graphviz DOT description of the graph for debugging,
+    /// nodes are labeled with their lattice coordinates and removed nodes are left out
+    /// built edges are solid, unbuilt ones dashed
+    pub fn to_dot(&self, built_only: bool) -> String {
+        let mut dot = String::from("graph board {\n");
+        for (id, vertex) in self.vertices.iter().enumerate() {
+            let Some(position) = vertex else { continue };
+            let (x, y) = position.coords;
+            dot += &format!("    {id} [label=\"({x}, {y})\"];\n");
+        }
+        for (from_id, to_id, built) in self.edges(built_only) {
+            let style = if built { "solid" } else { "dashed" };
+            dot += &format!("    {from_id} -- {to_id} [style={style}];\n");
+        }
+        dot += "}\n";
+        dot
+    }
+
+    /// every edge once as (from, to, built) with from <= to, sorted
+    /// the adjacency list has two nodes on the side between two empty tiles
+    /// connected over both tiles, and both ends of a closed loop on its node,
+    /// these are listed once as well
+    /// built_only: only the edges of paths drawn on placed tiles
+    pub fn edges(&self, built_only: bool) -> Vec<(usize, usize, bool)> {
+        let edges = self.adjacency_list.iter().enumerate().flat_map(
+            |(from_id, neighbors)| {
+                neighbors
+                    .iter()
+                    .map(move |&(to_id, built)| (from_id, to_id, built))
+            },
+        );
+        edges
+            .filter(|&(from_id, to_id, built)| {
+                from_id <= to_id && (built || !built_only)
+            })
+            .sorted_unstable()
+            .dedup()
+            .collect()
+    }
+
+    /// edges in the [2, E] edge index format of PyTorch Geometric
+    /// and the [E] built flags of the edges as their attributes
+    /// every edge is included in both directions, like in adjacency_list
+    pub fn edge_index_tensor(&self, built_only: bool) -> (PyTensor, PyTensor) {
+        let (mut from_ids, mut to_ids, mut built_flags) = (vec![], vec![], vec![]);
+        for (from_id, neighbors) in self.adjacency_list.iter().enumerate() {
+            for &(to_id, built) in neighbors {
+                if built_only && !built {
+                    continue;
+                }
+                from_ids.push(from_id as i64);
+                to_ids.push(to_id as i64);
+                built_flags.push(built);
+            }
+        }
+        let num_edges = built_flags.len() as i64;
+        let edge_index =
+            Tensor::from_slice(&[from_ids, to_ids].concat()).view([2, num_edges]);
+        (
+            PyTensor(edge_index),
+            PyTensor(Tensor::from_slice(&built_flags)),
+        )
+    }
+
+    /// per node features with shape [NUM_NODES, num_players + 3]
+    /// feature 0: 1 if the node is on the edge of the board
+    /// feature 1: 1 if the node has been removed (a path goes through it)
+    /// feature 2 + p: 1 if player p's marker is on the node
+    /// feature 2 + num_players: number of edges of the node
+    pub fn node_features_tensor(
+        &self,
+        board: &Board,
+        num_players: usize,
+    ) -> PyTensor {
+        let num_features = num_players + 3;
+        let mut features = vec![0f32; NUM_NODES * num_features];
+        for (x, y) in (0..LATTICE_SIZE).cartesian_product(0..LATTICE_SIZE) {
+            if (x % 3 == 0) ^ (y % 3 == 0) {
+                let position = MarkerPosition::from_lattice_coordinates((x, y));
+                let id = position.node_id();
+                let node = &mut features[id * num_features..][..num_features];
+                node[0] = position.is_edge() as u8 as f32;
+                node[1] = self.vertices[id].is_none() as u8 as f32;
+                node[2 + num_players] = self.adjacency_list[id].len() as f32;
+            }
+        }
+        for (player, marker) in board.markers.iter().enumerate().take(num_players) {
+            if let Some(marker) = marker {
+                let id = marker.position.node_id();
+                features[id * num_features + 2 + player] = 1.0;
+            }
+        }
+        PyTensor(
+            Tensor::from_slice(&features)
+                .view([NUM_NODES as i64, num_features as i64]),
+        )
     }
 }
 
@@ -166,3 +402,343 @@ fn test_graph() {
     let end = MarkerPosition::from_entry_point_index((0, 1), 0).node_id();
     assert!(graph.adjacency_list[end].contains(&(start, true)));
 }
+
+#[test]
+fn test_shortest_path() {
+    let mut graph = BoardGraph::new();
+    graph.place_tile(find_tile_with_connection(0, 5), (0, 0));
+    graph.place_tile(find_tile_with_connection(0, 5), (0, 1));
+    let start = MarkerPosition::from_entry_point_index((0, 0), 5).node_id();
+    let end = MarkerPosition::from_entry_point_index((0, 1), 0).node_id();
+    let far = MarkerPosition::from_entry_point_index((3, 3), 2).node_id();
+
+    assert_eq!(
+        graph.shortest_path(start, end, true),
+        Some((1, vec![start, end]))
+    );
+    assert_eq!(graph.shortest_path(start, far, true), None);
+
+    let (dist, path) = graph.shortest_path(start, far, false).unwrap();
+    assert_eq!(path.len(), dist + 1);
+    assert_eq!((path[0], path[dist]), (start, far));
+    for (a, b) in path.iter().tuple_windows() {
+        assert!(graph.adjacency_list[*a].iter().any(|(to_id, _)| to_id == b));
+    }
+    let bfs_dist = graph
+        .bfs_from(start, false)
+        .into_iter()
+        .find(|(_, id)| *id == far);
+    assert_eq!(bfs_dist, Some((dist, far)));
+}
+
+#[test]
+fn test_bfs_built_only() {
+    let mut graph = BoardGraph::new();
+    graph.place_tile(find_tile_with_connection(0, 5), (2, 2));
+    let start = MarkerPosition::from_entry_point_index((2, 2), 5).node_id();
+    let end = MarkerPosition::from_entry_point_index((2, 2), 0).node_id();
+
+    assert_eq!(graph.bfs_from(start, true), vec![(0, start), (1, end)]);
+    let all = graph.bfs_from(start, false);
+    assert_eq!(all.len(), NUM_NODES);
+    assert!(all.contains(&(1, end)));
+}
+
+#[test]
+fn test_connected_components() {
+    let mut graph = BoardGraph::new();
+    graph.place_tile(find_tile_with_connection(0, 5), (0, 0));
+    let middle = MarkerPosition::from_entry_point_index((0, 0), 0).node_id();
+    graph.place_tile(find_tile_with_connection(0, 5), (0, 1));
+    let start = MarkerPosition::from_entry_point_index((0, 0), 5).node_id();
+    let end = MarkerPosition::from_entry_point_index((0, 1), 0).node_id();
+    let other = MarkerPosition::from_entry_point_index((0, 0), 6).node_id();
+
+    let components = graph.connected_components(true);
+    assert_eq!(components[middle], usize::MAX);
+    assert_eq!(components[start], components[end]);
+    assert_ne!(components[start], components[other]);
+
+    let components = graph.connected_components(false);
+    assert_eq!(components[start], components[other]);
+}
+
+#[test]
+fn test_graph_eq_ignores_placement_order() {
+    use std::collections::hash_map::DefaultHasher;
+
+    let hash = |graph: &BoardGraph| {
+        let mut hasher = DefaultHasher::new();
+        graph.hash(&mut hasher);
+        hasher.finish()
+    };
+    let u_turn = find_tile_with_connection(0, 1);
+    let mut a = BoardGraph::new();
+    a.place_tile(u_turn, (0, 0));
+    a.place_tile(u_turn, (0, 1));
+    let mut b = BoardGraph::new();
+    b.place_tile(u_turn, (0, 1));
+    b.place_tile(u_turn, (0, 0));
+    // the closed loop between the tiles ends up on different nodes
+    assert_ne!(a.adjacency_list, b.adjacency_list);
+    assert_eq!(a, b);
+    assert_eq!(hash(&a), hash(&b));
+    assert_ne!(a, BoardGraph::new());
+}
+
+#[test]
+fn test_validate() {
+    let mut graph = BoardGraph::new();
+    assert_eq!(graph.validate(), Ok(()));
+    graph.place_tile(find_tile_with_connection(0, 5), (0, 0));
+    graph.place_tile(find_tile_with_connection(0, 1), (0, 1));
+    graph.place_tile(find_tile_with_connection(2, 6), (1, 1));
+    assert_eq!(graph.validate(), Ok(()));
+
+    let start = MarkerPosition::from_entry_point_index((0, 0), 5).node_id();
+    let mut one_sided = graph.clone();
+    one_sided.adjacency_list[start].pop();
+    assert!(one_sided.validate().is_err());
+
+    let mut wrong_flag = graph.clone();
+    wrong_flag.adjacency_list[start][0].1 = false;
+    assert!(wrong_flag.validate().is_err());
+
+    let mut removed = graph;
+    removed.vertices[start] = None;
+    assert!(removed.validate().is_err());
+}
+
+#[test]
+fn test_node_features_tensor() {
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_marker(20);
+    let tile = find_tile_with_connection(0, 5);
+    board.graph.place_tile(tile, (0, 0));
+    board.graph.place_tile(tile, (0, 1));
+    let features = board.graph.node_features_tensor(&board, 2);
+    assert_eq!(features.size(), [NUM_NODES as i64, 5]);
+    let feature =
+        |id: usize, feature: i64| features.double_value(&[id as i64, feature]);
+
+    let edge_nodes = (0..NUM_NODES).filter(|id| feature(*id, 0) == 1.0).count();
+    assert_eq!(edge_nodes, NUM_EDGE_POSITIONS);
+
+    let removed = MarkerPosition::from_entry_point_index((0, 0), 0).node_id();
+    let start = MarkerPosition::from_entry_point_index((0, 0), 5).node_id();
+    assert_eq!((feature(removed, 1), feature(removed, 4)), (1.0, 0.0));
+    assert_eq!((feature(start, 1), feature(start, 4)), (0.0, 1.0));
+
+    let first = MarkerPosition::from_index(0).node_id();
+    let second = MarkerPosition::from_index(20).node_id();
+    assert_eq!((feature(first, 2), feature(first, 3)), (1.0, 0.0));
+    assert_eq!((feature(second, 2), feature(second, 3)), (0.0, 1.0));
+}
+
+#[test]
+fn test_edge_index_tensor() {
+    let mut graph = BoardGraph::new();
+    let tile = find_tile_with_connection(0, 5);
+    graph.place_tile(tile, (0, 0));
+    graph.place_tile(tile, (0, 1));
+    let num_edges: usize = graph.adjacency_list.iter().map(Vec::len).sum();
+    let num_built = graph
+        .adjacency_list
+        .iter()
+        .flatten()
+        .filter(|(_, built)| *built)
+        .count();
+
+    let (edge_index, built) = graph.edge_index_tensor(false);
+    assert_eq!(edge_index.size(), [2, num_edges as i64]);
+    assert_eq!(built.size(), [num_edges as i64]);
+    assert_eq!(
+        built.sum(tch::Kind::Int64).int64_value(&[]),
+        num_built as i64
+    );
+    for edge in 0..num_edges as i64 {
+        let from_id = edge_index.int64_value(&[0, edge]) as usize;
+        let to_id = edge_index.int64_value(&[1, edge]) as usize;
+        let flag = built.int64_value(&[edge]) == 1;
+        assert!(graph.adjacency_list[from_id].contains(&(to_id, flag)));
+    }
+
+    let (edge_index, built) = graph.edge_index_tensor(true);
+    assert_eq!(edge_index.size(), [2, num_built as i64]);
+    assert_eq!(
+        built.sum(tch::Kind::Int64).int64_value(&[]),
+        num_built as i64
+    );
+}
+
+#[test]
+fn test_to_dot() {
+    let mut graph = BoardGraph::new();
+    graph.place_tile(find_tile_with_connection(0, 5), (0, 0));
+    graph.place_tile(find_tile_with_connection(0, 5), (0, 1));
+    let start = MarkerPosition::from_entry_point_index((0, 0), 5);
+    let end = MarkerPosition::from_entry_point_index((0, 1), 0);
+    let removed = MarkerPosition::from_entry_point_index((0, 0), 0).node_id();
+    let (start_id, end_id) = (start.node_id(), end.node_id());
+
+    let dot = graph.to_dot(false);
+    assert!(dot.starts_with("graph board {\n") && dot.ends_with("}\n"));
+    let (x, y) = start.coords;
+    assert!(dot.contains(&format!("    {start_id} [label=\"({x}, {y})\"];\n")));
+    assert!(!dot.contains(&format!("    {removed} [")));
+    let (a, b) = (start_id.min(end_id), start_id.max(end_id));
+    assert_eq!(
+        dot.matches(&format!("    {a} -- {b} [style=solid];"))
+            .count(),
+        1
+    );
+    assert!(dot.contains("[style=dashed]"));
+
+    let built_only = graph.to_dot(true);
+    assert!(!built_only.contains("dashed"));
+    let num_built = graph
+        .adjacency_list
+        .iter()
+        .flatten()
+        .filter(|e| e.1)
+        .count();
+    assert_eq!(built_only.matches(" -- ").count(), num_built / 2);
+}
+
+#[test]
+fn test_from_node_id() {
+    for (x, y) in (0..LATTICE_SIZE).cartesian_product(0..LATTICE_SIZE) {
+        if (x % 3 == 0) ^ (y % 3 == 0) {
+            let position = MarkerPosition::from_lattice_coordinates((x, y));
+            let id = position.node_id();
+            assert_eq!(MarkerPosition::from_node_id(id).unwrap(), position);
+        }
+    }
+    assert!(MarkerPosition::from_node_id(NUM_NODES).is_err());
+}
+
+#[test]
+fn test_all_nodes_order() {
+    assert_eq!(ALL_NODES.len(), NUM_NODES);
+    for (id, position) in ALL_NODES.iter().enumerate() {
+        assert_eq!(position.node_id(), id);
+    }
+}
+
+#[test]
+fn test_stack_adjacency_tensors() {
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_tile(Tile::from_code("16-25-38-47").unwrap(), 0);
+    let graphs = vec![Board::new().graph, board.graph];
+    let stacked = crate::stack_adjacency_tensors(graphs.clone()).0;
+    let size = NUM_NODES as i64;
+    assert_eq!(stacked.size(), [2, size, size]);
+    for (idx, graph) in graphs.iter().enumerate() {
+        assert_eq!(stacked.get(idx as i64), graph.adjacency_matrix_tensor().0);
+    }
+    assert_eq!(
+        crate::stack_adjacency_tensors(vec![]).0.size(),
+        [0, size, size]
+    );
+}
+
+/// run with cargo test --release -- --ignored --nocapture
+#[test]
+#[ignore]
+fn bench_stack_adjacency_tensors() {
+    use std::hint::black_box;
+    use std::time::Instant;
+
+    let mut env = TsuroEnv::new(Some(4), Some(0), EnvConfig::default()).unwrap();
+    for position in [0, 12, 24, 36] {
+        env.step_place_marker(position).unwrap();
+    }
+    for _ in 0..10 {
+        let tile = env.legal_actions()[0];
+        env.step_place_tile(tile).unwrap();
+    }
+    let graphs = vec![env.board.graph.clone(); 64];
+    let iterations = 1_000;
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        let tensors = graphs
+            .iter()
+            .map(|graph| graph.adjacency_matrix_tensor().0)
+            .collect_vec();
+        black_box(Tensor::stack(&tensors, 0));
+    }
+    println!("individual: {:?}", start.elapsed() / iterations);
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(crate::stack_adjacency_tensors(graphs.clone()));
+    }
+    println!("stacked:    {:?}", start.elapsed() / iterations);
+}
+
+#[test]
+fn test_adjacency_matrix_tensor() {
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_tile(Tile::from_code("16-25-38-47").unwrap(), 0);
+    let graph = &board.graph;
+    let tensor = graph.adjacency_matrix_tensor().0;
+    let size = NUM_NODES as i64;
+    assert_eq!(tensor.size(), [size, size]);
+    assert_eq!(tensor.kind(), tch::Kind::Int8);
+    for from_id in 0..NUM_NODES {
+        for to_id in 0..NUM_NODES {
+            let edge = graph.adjacency_list[from_id]
+                .iter()
+                .find(|(id, _built)| *id == to_id);
+            let expected = match edge {
+                Some((_id, true)) => 1,
+                Some((_id, false)) => -1,
+                None => 0,
+            };
+            let value = tensor.int64_value(&[from_id as i64, to_id as i64]);
+            assert_eq!(value, expected);
+        }
+    }
+}
+
+#[test]
+fn test_edges() {
+    let u_turn = find_tile_with_connection(0, 1);
+    let mut graph = BoardGraph::new();
+    graph.place_tile(u_turn, (0, 0));
+    graph.place_tile(u_turn, (0, 1));
+    let edges = graph.edges(false);
+    assert!(edges.iter().all_unique());
+    assert!(edges.iter().all(|(from_id, to_id, _)| from_id <= to_id));
+    for (from_id, neighbors) in graph.adjacency_list.iter().enumerate() {
+        for (to_id, built) in neighbors {
+            let edge = (from_id.min(*to_id), from_id.max(*to_id), *built);
+            assert!(edges.contains(&edge));
+        }
+    }
+    let num_edge_ends: usize = graph.adjacency_list.iter().map(Vec::len).sum();
+    assert!(edges.len() < num_edge_ends / 2);
+
+    // connected over the empty tiles on both sides, but listed once
+    let mut side = [2, 3].map(|entry_point| {
+        MarkerPosition::from_entry_point_index((1, 1), entry_point).node_id()
+    });
+    side.sort_unstable();
+    let parallel = graph.adjacency_list[side[0]].iter();
+    assert_eq!(parallel.filter(|(to_id, _)| *to_id == side[1]).count(), 2);
+    let listed = edges
+        .iter()
+        .filter(|edge| **edge == (side[0], side[1], false));
+    assert_eq!(listed.count(), 1);
+
+    let built = graph.edges(true);
+    assert!(built.iter().all(|(_, _, built)| *built));
+    // the loop the u turns close between the tiles is listed once
+    let loops = built.iter().filter(|(from_id, to_id, _)| from_id == to_id);
+    assert_eq!(loops.count(), 1);
+    assert_eq!(built.len(), 7);
+}
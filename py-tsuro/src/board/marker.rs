@@ -3,11 +3,11 @@ mod edge_index_to_position;
 use crate::*;
 use arrayvec::ArrayVec;
 use edge_index_to_position::INDEX_TO_POSITION;
+use pyo3::exceptions::PyValueError;
 
 impl MarkerPosition {
     pub fn from_index(idx: usize) -> Self {
         MarkerPosition {
-            #[allow(clippy::borrow_interior_mutable_const)]
             coords: INDEX_TO_POSITION[idx],
         }
     }
@@ -39,29 +39,37 @@ impl MarkerPosition {
         Self { coords: position }
     }
 
+    /// None if the coordinates are not the midpoint of a tile side on the board
+    pub fn try_from_lattice_coordinates(position: Coord) -> Option<Self> {
+        let (x, y) = position;
+        let valid =
+            x < LATTICE_SIZE && y < LATTICE_SIZE && ((x % 3 == 0) ^ (y % 3 == 0));
+        valid.then(|| Self::from_lattice_coordinates(position))
+    }
+
     /// adjacent coordinates, with corresponding entry point indices
     /// if there is only one, it returns that twice
     pub fn entry_point_indices(&self) -> ArrayVec<(Coord, usize), 2> {
         let (x, y) = self.coords;
 
-        if x == 18 {
+        if x == LATTICE_SIZE - 1 {
             let local_y = y % 3;
             let entry_point = match local_y {
                 1 => 3,
                 2 => 2,
                 _ => panic!("Invalid lattice coordinates"),
             };
-            let ret = ((5, y / 3), entry_point);
+            let ret = ((BOARD_SIZE - 1, y / 3), entry_point);
             return ArrayVec::from_iter([ret]);
         }
-        if y == 18 {
+        if y == LATTICE_SIZE - 1 {
             let local_x = x % 3;
             let entry_point = match local_x {
                 1 => 0,
                 2 => 1,
                 _ => panic!("Invalid lattice coordinates"),
             };
-            let ret = ((x / 3, 5), entry_point);
+            let ret = ((x / 3, BOARD_SIZE - 1), entry_point);
             return ArrayVec::from_iter([ret]);
         }
 
@@ -104,18 +112,6 @@ impl MarkerPosition {
         ])
     }
 
-    pub fn entry_point_index_on(&self, pos: Coord) -> Option<usize> {
-        let (positions, entry_indices): (Vec<_>, Vec<_>) =
-            self.entry_point_indices().into_iter().unzip();
-        if pos == positions[0] {
-            return Some(entry_indices[0]);
-        } else if pos == positions[1] {
-            return Some(entry_indices[1]);
-        } else {
-            return None;
-        }
-    }
-
     pub fn adjacent_tiles(&self) -> ArrayVec<Coord, 2> {
         self.entry_point_indices()
             .into_iter()
@@ -127,7 +123,60 @@ impl MarkerPosition {
 #[pymethods]
 impl MarkerPosition {
     pub fn is_edge(&self) -> bool {
-        matches!(self.coords, (0, _) | (18, _) | (_, 0) | (_, 18))
+        let last = LATTICE_SIZE - 1;
+        matches!(self.coords, (0, _) | (_, 0))
+            || self.coords.0 == last
+            || self.coords.1 == last
+    }
+
+    /// entry point index of this position on the tile at pos,
+    /// None if the tile is not adjacent
+    pub fn entry_point_index_on(&self, pos: Coord) -> Option<usize> {
+        self.entry_point_indices()
+            .into_iter()
+            .find(|(tile_coord, _entry_idx)| *tile_coord == pos)
+            .map(|(_tile_coord, entry_idx)| entry_idx)
+    }
+
+    #[pyo3(name = "adjacent_tiles")]
+    fn py_adjacent_tiles(&self) -> Vec<Coord> {
+        self.adjacent_tiles().to_vec()
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "from_index")]
+    fn py_from_index(idx: usize) -> PyResult<Self> {
+        if idx >= NUM_EDGE_POSITIONS {
+            return Err(PyErr::new::<PyValueError, _>(format!(
+                "edge index {idx} is out of range 0..{NUM_EDGE_POSITIONS}"
+            )));
+        }
+        Ok(Self::from_index(idx))
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "from_entry_point_index")]
+    fn py_from_entry_point_index(
+        position: Coord,
+        entry_point: usize,
+    ) -> PyResult<Self> {
+        let (x, y) = position;
+        if x >= BOARD_SIZE || y >= BOARD_SIZE || entry_point >= 8 {
+            return Err(PyErr::new::<PyValueError, _>(format!(
+                "invalid entry point {entry_point} on tile {position:?}"
+            )));
+        }
+        Ok(Self::from_entry_point_index(position, entry_point))
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "from_lattice_coordinates")]
+    fn py_from_lattice_coordinates(coords: Coord) -> PyResult<Self> {
+        Self::try_from_lattice_coordinates(coords).ok_or_else(|| {
+            PyErr::new::<PyValueError, _>(format!(
+                "{coords:?} is not a valid marker position"
+            ))
+        })
     }
 }
 
@@ -140,6 +189,7 @@ impl Default for MarkerPosition {
 }
 
 #[test]
+#[cfg(not(feature = "board-5x5"))] // coordinates on the 6x6 board
 fn test_from_entry_point_indices() {
     let tests = [
         (3, 4),
@@ -166,3 +216,54 @@ fn test_from_entry_point_indices() {
         assert_eq!(*mp.entry_point_indices(), *answer)
     }
 }
+
+#[test]
+fn test_python_constructors_validate() {
+    assert_eq!(
+        MarkerPosition::py_from_index(NUM_EDGE_POSITIONS - 1).unwrap(),
+        MarkerPosition::from_index(NUM_EDGE_POSITIONS - 1)
+    );
+    assert!(MarkerPosition::py_from_index(NUM_EDGE_POSITIONS).is_err());
+    let last = BOARD_SIZE - 1;
+    assert!(MarkerPosition::py_from_entry_point_index((last, last), 7).is_ok());
+    assert!(MarkerPosition::py_from_entry_point_index((BOARD_SIZE, 0), 0).is_err());
+    assert!(MarkerPosition::py_from_entry_point_index((0, 0), 8).is_err());
+    assert!(MarkerPosition::py_from_lattice_coordinates((3, 4)).is_ok());
+    assert!(MarkerPosition::py_from_lattice_coordinates((3, 3)).is_err());
+    assert!(MarkerPosition::py_from_lattice_coordinates((4, 4)).is_err());
+    assert!(MarkerPosition::py_from_lattice_coordinates((LATTICE_SIZE, 1)).is_err());
+}
+
+#[test]
+fn test_try_from_lattice_coordinates() {
+    let position = MarkerPosition::try_from_lattice_coordinates((3, 4));
+    assert_eq!(
+        position,
+        Some(MarkerPosition::from_lattice_coordinates((3, 4)))
+    );
+    assert_eq!(MarkerPosition::try_from_lattice_coordinates((3, 3)), None);
+    assert_eq!(MarkerPosition::try_from_lattice_coordinates((4, 4)), None);
+    let last = LATTICE_SIZE - 1;
+    assert!(MarkerPosition::try_from_lattice_coordinates((last, 1)).is_some());
+    assert_eq!(
+        MarkerPosition::try_from_lattice_coordinates((last + 3, 1)),
+        None
+    );
+    assert_eq!(
+        MarkerPosition::try_from_lattice_coordinates((1, usize::MAX)),
+        None
+    );
+}
+
+#[test]
+fn test_adjacent_tiles() {
+    let edge = MarkerPosition::from_lattice_coordinates((0, 1));
+    assert_eq!(edge.py_adjacent_tiles(), vec![(0, 0)]);
+    assert_eq!(edge.entry_point_index_on((0, 0)), Some(6));
+    assert_eq!(edge.entry_point_index_on((1, 0)), None);
+
+    let interior = MarkerPosition::from_lattice_coordinates((3, 4));
+    assert_eq!(interior.py_adjacent_tiles(), vec![(1, 1), (0, 1)]);
+    assert_eq!(interior.entry_point_index_on((0, 1)), Some(3));
+    assert_eq!(interior.entry_point_index_on((2, 1)), None);
+}
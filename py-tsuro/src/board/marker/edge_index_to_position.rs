@@ -1,43 +1,58 @@
 use super::Coord;
-use std::cell::LazyCell;
+use crate::{LATTICE_SIZE, NUM_EDGE_POSITIONS};
+use std::sync::LazyLock;
 
 /// Convert from an index on the edge of the board to a position
 /// 0 is the bottom left corner -> (1; 18)
-#[allow(clippy::declare_interior_mutable_const)]
-pub const INDEX_TO_POSITION: LazyCell<[Coord; 48]> = LazyCell::new(|| {
-    let mut out = [(usize::MAX, usize::MAX); 48];
-    let mut idx = 0;
-    // y = 18
-    for x in 0..18 {
-        if x % 3 == 0 {
-            continue;
+pub static INDEX_TO_POSITION: LazyLock<[Coord; NUM_EDGE_POSITIONS]> =
+    LazyLock::new(|| {
+        let last = LATTICE_SIZE - 1;
+        let mut out = [(usize::MAX, usize::MAX); NUM_EDGE_POSITIONS];
+        let mut idx = 0;
+        // y = last
+        for x in 0..last {
+            if x % 3 == 0 {
+                continue;
+            }
+            out[idx] = (x, last);
+            idx += 1;
         }
-        out[idx] = (x, 18);
-        idx += 1;
-    }
-    // x = 18
-    for y_inv in 0..18 {
-        if y_inv % 3 == 0 {
-            continue;
+        // x = last
+        for y_inv in 0..last {
+            if y_inv % 3 == 0 {
+                continue;
+            }
+            out[idx] = (last, last - y_inv);
+            idx += 1;
         }
-        out[idx] = (18, 18 - y_inv);
-        idx += 1;
-    }
-    // y = 0
-    for x_inv in 0..18 {
-        if x_inv % 3 == 0 {
-            continue;
+        // y = 0
+        for x_inv in 0..last {
+            if x_inv % 3 == 0 {
+                continue;
+            }
+            out[idx] = (last - x_inv, 0);
+            idx += 1;
         }
-        out[idx] = (18 - x_inv, 0);
-        idx += 1;
-    }
-    // x = 0
-    for y in 0..18 {
-        if y % 3 == 0 {
-            continue;
+        // x = 0
+        for y in 0..last {
+            if y % 3 == 0 {
+                continue;
+            }
+            out[idx] = (0, y);
+            idx += 1;
         }
-        out[idx] = (0, y);
-        idx += 1;
-    }
-    out
-});
+        out
+    });
+
+#[test]
+fn test_index_to_position_order() {
+    let last = LATTICE_SIZE - 1;
+    // counterclockwise starting from the bottom left corner
+    assert_eq!(INDEX_TO_POSITION[0], (1, last));
+    assert_eq!(INDEX_TO_POSITION[1], (2, last));
+    let side = NUM_EDGE_POSITIONS / 4;
+    assert_eq!(INDEX_TO_POSITION[side], (last, last - 1));
+    assert_eq!(INDEX_TO_POSITION[2 * side], (last - 1, 0));
+    assert_eq!(INDEX_TO_POSITION[3 * side], (0, 1));
+    assert_eq!(INDEX_TO_POSITION[NUM_EDGE_POSITIONS - 1], (0, last - 1));
+}
@@ -0,0 +1,239 @@
+use crate::*;
+use itertools::Itertools;
+
+#[cfg(test)]
+use crate::find_tile_with_connection;
+
+/// width of a tile in characters, including one border
+const CELL_WIDTH: usize = 8;
+/// height of a tile in characters, including one border
+const CELL_HEIGHT: usize = 5;
+
+/// (row, column) of the character a lattice point is drawn on
+fn lattice_to_char((x, y): Coord) -> (usize, usize) {
+    let column = x / 3 * CELL_WIDTH + [0, 3, 5][x % 3];
+    let row = y / 3 * CELL_HEIGHT + [0, 2, 3][y % 3];
+    (row, column)
+}
+
+fn border_char(row: usize, column: usize) -> char {
+    let on_row = row.is_multiple_of(CELL_HEIGHT);
+    let on_column = column.is_multiple_of(CELL_WIDTH);
+    let (last_row, last_column) =
+        (BOARD_SIZE * CELL_HEIGHT, BOARD_SIZE * CELL_WIDTH);
+    match (on_row, on_column) {
+        (true, true) => match (row, column) {
+            (0, 0) => '┌',
+            (0, c) if c == last_column => '┐',
+            (0, _) => '┬',
+            (r, 0) if r == last_row => '└',
+            (r, c) if r == last_row && c == last_column => '┘',
+            (r, _) if r == last_row => '┴',
+            (_, 0) => '├',
+            (_, c) if c == last_column => '┤',
+            _ => '┼',
+        },
+        (true, false) => '─',
+        (false, true) => '│',
+        (false, false) => ' ',
+    }
+}
+
+/// colors of the pawns in svg output, indexed by player
+const PLAYER_COLORS: [&str; 8] = [
+    "#e6194b", "#3cb44b", "#4363d8", "#f58231", "#911eb4", "#42d4f4", "#f032e6",
+    "#9a6324",
+];
+
+#[pymethods]
+impl Board {
+    /// grid of tile codes (see Tile.code), '.' for empty cells, one row per line
+    /// use render_ascii to see the paths, format!("{:?}") for everything
+    pub fn __str__(&self) -> String {
+        let code_width = "12-34-56-78".len();
+        let mut rows = (0..BOARD_SIZE).map(|y| {
+            (0..BOARD_SIZE)
+                .map(|x| match self.tiles[x][y] {
+                    Some(tile) => tile.code(),
+                    None => format!("{:<code_width$}", "."),
+                })
+                .join(" ")
+                .trim_end()
+                .to_owned()
+        });
+        let markers = self.num_survivors();
+        format!("Board({markers} markers)\n{}", rows.join("\n"))
+    }
+
+    pub fn __repr__(&self) -> String {
+        self.__str__()
+    }
+
+    /// draw the board with box-drawing characters
+    /// the two ends of each path on a tile are marked with the same letter
+    /// markers are drawn as the player index on the border they are on,
+    /// in base 36: players 0 to 9 as digits, 10 to 35 as the letters a to z,
+    /// and '?' from player 36 up, which games never have but from_placements allows
+    #[pyo3(signature = (show_markers=true))]
+    pub fn render_ascii(&self, show_markers: bool) -> String {
+        let mut chars: Vec<Vec<char>> = (0..=BOARD_SIZE * CELL_HEIGHT)
+            .map(|row| {
+                (0..=BOARD_SIZE * CELL_WIDTH)
+                    .map(|column| border_char(row, column))
+                    .collect()
+            })
+            .collect();
+
+        for (x, row) in self.tiles.iter().enumerate() {
+            for (y, tile) in row.iter().enumerate() {
+                let Some(tile) = tile else { continue };
+                for (path, (a, b)) in tile.paths().into_iter().enumerate() {
+                    let letter = (b'a' + path as u8) as char;
+                    for entry_point in [a, b] {
+                        let position = MarkerPosition::from_entry_point_index(
+                            (x, y),
+                            entry_point,
+                        );
+                        let (mut row, mut column) = lattice_to_char(position.coords);
+                        // move off the border to the inside of this tile
+                        match entry_point {
+                            0 | 1 => row -= 1,
+                            2 | 3 => column -= 1,
+                            4 | 5 => row += 1,
+                            _ => column += 1,
+                        }
+                        chars[row][column] = letter;
+                    }
+                }
+            }
+        }
+
+        if show_markers {
+            for (player, marker) in self.markers.iter().enumerate() {
+                let Some(marker) = marker else { continue };
+                let (row, column) = lattice_to_char(marker.position.coords);
+                chars[row][column] =
+                    char::from_digit(player as u32, 36).unwrap_or('?');
+            }
+        }
+
+        chars
+            .into_iter()
+            .map(|line| line.into_iter().collect::<String>())
+            .join("\n")
+    }
+
+    /// svg image of the board with the paths of placed tiles and the pawns
+    /// width: width and height of the image in pixels
+    #[pyo3(signature = (width=600.0))]
+    pub fn to_svg(&self, width: f64) -> String {
+        let tile_size = width / BOARD_SIZE as f64;
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+            width=\"{width}\" height=\"{width}\" viewBox=\"0 0 {width} {width}\">\n\
+            <rect width=\"{width}\" height=\"{width}\" fill=\"#f5f0e1\"/>\n"
+        );
+        for (x, row) in self.tiles.iter().enumerate() {
+            for (y, tile) in row.iter().enumerate() {
+                let corner = (x as f64 * tile_size, y as f64 * tile_size);
+                let fill = if tile.is_some() { "#d9c9a3" } else { "none" };
+                svg += &format!(
+                    "<rect x=\"{:.3}\" y=\"{:.3}\" \
+                    width=\"{tile_size:.3}\" height=\"{tile_size:.3}\" \
+                    fill=\"{fill}\" stroke=\"#8c7b5a\"/>\n",
+                    corner.0, corner.1
+                );
+                let Some(tile) = tile else { continue };
+                for data in tile.svg_path_data(corner, tile_size) {
+                    svg += &format!(
+                        "<path d=\"{data}\" fill=\"none\" stroke=\"#ffffff\" \
+                        stroke-width=\"{:.3}\"/>\n",
+                        tile_size / 20.0
+                    );
+                }
+            }
+        }
+        for (player, marker) in self.markers.iter().enumerate() {
+            let Some(marker) = marker else { continue };
+            let (x, y) = marker.position.coords;
+            svg += &format!(
+                "<circle cx=\"{:.3}\" cy=\"{:.3}\" r=\"{:.3}\" fill=\"{}\" \
+                stroke=\"#000000\"/>\n",
+                x as f64 * tile_size / 3.0,
+                y as f64 * tile_size / 3.0,
+                tile_size / 8.0,
+                PLAYER_COLORS[player % PLAYER_COLORS.len()]
+            );
+        }
+        svg += "</svg>\n";
+        svg
+    }
+}
+
+#[test]
+fn test_render_ascii() {
+    let empty = Board::new().render_ascii(true);
+    let lines = empty.lines().collect_vec();
+    assert_eq!(lines.len(), BOARD_SIZE * CELL_HEIGHT + 1);
+    assert!(lines
+        .iter()
+        .all(|line| line.chars().count() == BOARD_SIZE * CELL_WIDTH + 1));
+    assert!(lines[0].starts_with('┌') && lines[0].ends_with('┐'));
+
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_marker(20);
+    let tile = Tile::from_code("12-34-56-78").unwrap();
+    board.place_tile(tile, 0);
+    let rendered = board.render_ascii(true);
+    for letter in ['a', 'b', 'c', 'd'] {
+        assert_eq!(rendered.matches(letter).count(), 2);
+    }
+    assert_eq!(rendered.matches('0').count(), 1);
+    assert_eq!(rendered.matches('1').count(), 1);
+
+    let hidden = board.render_ascii(false);
+    assert!(!hidden.contains('0') && !hidden.contains('1'));
+
+    let every_edge = (0..NUM_EDGE_POSITIONS).collect_vec();
+    let full = Board::from_placements(vec![], every_edge).unwrap();
+    let rendered = full.render_ascii(true);
+    assert_eq!(rendered.matches('a').count(), 1); // player 10
+    assert_eq!(rendered.matches('z').count(), 1); // player 35
+    assert_eq!(rendered.matches('?').count(), NUM_EDGE_POSITIONS - 36);
+}
+
+#[test]
+fn test_to_svg() {
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_marker(20);
+    board.eliminate_player(1);
+    board.place_tile(find_tile_with_connection(0, 5), 0);
+    let svg = board.to_svg(600.0);
+    assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+    assert_eq!(svg.matches("<path").count(), 4);
+    assert_eq!(svg.matches("<circle").count(), 1);
+    assert_eq!(svg.matches("<rect").count(), BOARD_SIZE * BOARD_SIZE + 1);
+}
+
+#[test]
+fn test_board_str() {
+    let empty = Board::new().__str__();
+    let lines = empty.lines().collect_vec();
+    assert_eq!(lines.len(), BOARD_SIZE + 1);
+    assert_eq!(lines[0], "Board(0 markers)");
+    assert!(lines[1].starts_with('.') && !lines[1].contains('-'));
+
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_marker(20);
+    let tile = Tile::from_code("12-34-56-78").unwrap();
+    let (x, y) = board.next_tile_of_player(0);
+    board.place_tile(tile, 0);
+    let rendered = board.__repr__();
+    assert!(rendered.starts_with("Board(2 markers)\n"));
+    let row = rendered.lines().nth(y + 1).unwrap();
+    assert_eq!(row.split_whitespace().nth(x), Some("12-34-56-78"));
+    assert!(!rendered.contains("adjacency_list"));
+}
@@ -0,0 +1,89 @@
+use itertools::Itertools;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use std::sync::LazyLock;
+
+use crate::*;
+
+/// most markers a board can hold, one on each edge position,
+/// from_placements accepts that many even though a game has at most 8 players
+const MAX_MARKERS: usize = NUM_EDGE_POSITIONS;
+
+struct ZobristTable {
+    /// indexed by cell, then ALL_TILES index * 4 + rotation
+    tiles: Vec<[u64; 35 * 4]>,
+    /// indexed by player, then node id of the marker position
+    markers: Vec<[u64; NUM_NODES]>,
+}
+
+static ZOBRIST_TABLE: LazyLock<ZobristTable> = LazyLock::new(|| {
+    // fixed seed so hashes are the same across runs
+    let mut rng = ChaCha12Rng::seed_from_u64(0x7375726f);
+    let tiles = (0..BOARD_SIZE * BOARD_SIZE)
+        .map(|_| std::array::from_fn(|_| rng.random()))
+        .collect();
+    let markers = (0..MAX_MARKERS)
+        .map(|_| std::array::from_fn(|_| rng.random()))
+        .collect();
+    ZobristTable { tiles, markers }
+});
+
+#[pymethods]
+impl Board {
+    /// 64 bit key of the placed tiles and marker positions,
+    /// the same no matter in which order the board was built
+    /// it does not depend on whose turn it is or on the hands
+    pub fn zobrist_hash(&self) -> u64 {
+        let table = &*ZOBRIST_TABLE;
+        let mut hash = 0;
+        for (x, y) in (0..BOARD_SIZE).cartesian_product(0..BOARD_SIZE) {
+            let Some(tile) = self.tiles[x][y] else {
+                continue;
+            };
+            let index = tile
+                .index()
+                .expect("placed tiles should be rotations of ALL_TILES");
+            let rotation = (0..4)
+                .find(|rot| ALL_TILES[index].rotated(*rot) == tile)
+                .unwrap();
+            hash ^= table.tiles[x * BOARD_SIZE + y][index * 4 + rotation];
+        }
+        for (player, marker) in self.markers.iter().enumerate() {
+            let Some(marker) = marker else { continue };
+            hash ^= table.markers[player][marker.position.node_id()];
+        }
+        hash
+    }
+}
+
+#[test]
+fn test_zobrist_hash() {
+    let straight = Tile::from_code("16-25-38-47").unwrap();
+    let other = Tile::from_code("12-37-48-56").unwrap();
+    let placements = vec![((0, 0), straight), ((3, 2), other), ((4, 1), other)];
+    let mut reversed = placements.clone();
+    reversed.reverse();
+    let board = Board::from_placements(placements.clone(), vec![2, 30]).unwrap();
+    let same = Board::from_placements(reversed, vec![2, 30]).unwrap();
+    assert_eq!(board.zobrist_hash(), same.zobrist_hash());
+    assert_ne!(board.zobrist_hash(), Board::new().zobrist_hash());
+
+    let mut rotated = placements.clone();
+    rotated[1].1 = other.rotated(1);
+    let rotated = Board::from_placements(rotated, vec![2, 30]).unwrap();
+    assert_ne!(board.zobrist_hash(), rotated.zobrist_hash());
+
+    let swapped = Board::from_placements(placements, vec![30, 2]).unwrap();
+    assert_ne!(board.zobrist_hash(), swapped.zobrist_hash());
+}
+
+#[test]
+fn test_zobrist_hash_every_edge_position() {
+    let full = (0..NUM_EDGE_POSITIONS).collect_vec();
+    let board = Board::from_placements(vec![], full).unwrap();
+    assert_eq!(board.markers.len(), NUM_EDGE_POSITIONS);
+    let mut reversed = (0..NUM_EDGE_POSITIONS).collect_vec();
+    reversed.reverse();
+    let reversed = Board::from_placements(vec![], reversed).unwrap();
+    assert_ne!(board.zobrist_hash(), reversed.zobrist_hash());
+}
@@ -1,95 +1,387 @@
+use crate::board::GraphPatch;
 use crate::*;
 
 use itertools::Itertools;
-use rand::{seq::SliceRandom, rng};
+use pyo3_tch::PyTensor;
+use rand::{rng, seq::IndexedRandom, seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use std::iter::zip;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tch::Tensor;
+
+/// range of player counts in the official rules
+const PLAYER_RANGE: std::ops::RangeInclusive<usize> = 2..=8;
+
+/// next UndoRecord id, shared by every env so clones never reuse an id
+/// starts at a random value so ids of envs unpickled from other processes
+/// are unlikely to collide either
+static NEXT_UNDO_ID: LazyLock<AtomicU64> =
+    LazyLock::new(|| AtomicU64::new(rand::random()));
+
+/// state changed by step_place_tile or step_pass,
+/// the graph is only partially recorded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct UndoRecord {
+    /// identifies the line of play up to this record, see Snapshot.undo_top
+    id: u64,
+    /// cell of the placed tile and the graph around it, None for a pass
+    placement: Option<(Coord, GraphPatch)>,
+    markers: Vec<Option<Marker>>,
+    player_hands: Vec<Vec<Tile>>,
+    deck: Vec<Tile>,
+    dragon_tile_owner: Option<usize>,
+    num_players_left: usize,
+    active_player: usize,
+    rng: ChaCha12Rng,
+}
+
+/// ignores the undo records, two envs in the same state are equal
+/// no matter which moves they can still undo
+impl PartialEq for TsuroEnv {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board
+            && self.num_markers_placed == other.num_markers_placed
+            && self.phase == other.phase
+            && self.num_players == other.num_players
+            && self.active_player == other.active_player
+            && self.player_hands == other.player_hands
+            && self.deck == other.deck
+            && self.dragon_tile_owner == other.dragon_tile_owner
+            && self.num_players_left == other.num_players_left
+            && self.hand_size == other.hand_size
+            && self.rng == other.rng
+            && self.record_undo == other.record_undo
+            && self.record_history == other.record_history
+            && self.history == other.history
+            && self.frame_history == other.frame_history
+            && self.frames == other.frames
+            && self.reward_config == other.reward_config
+    }
+}
 
 #[pymethods]
 impl TsuroEnv {
     /// takes Option<usize> so calling __new__() works.
     /// required because of unpickling
+    /// if seed is given, all shuffling of the deck is reproducible
+    /// record_undo enables undo and restore past tile placements
+    /// frame_history is the most frames stacked_observation can stack
+    /// reward_config defaults to RewardConfig(), sparse win/loss rewards
     #[new]
-    pub fn new(num_players: Option<usize>) -> Self {
-        if num_players.is_none() {
-            return Self::default();
-        }
-        let num_players = num_players.unwrap();
-        #[allow(clippy::borrow_interior_mutable_const)]
-        let mut deck = ALL_TILES.into_iter().collect_vec();
-        deck.shuffle(&mut rng());
-        let mut player_hands = vec![Vec::new(); num_players];
-        for hand in player_hands.iter_mut() {
-            hand.append(&mut deck.split_off(deck.len() - 3));
-        }
-        TsuroEnv {
-            board: Board::new(),
-            num_markers_placed: 0,
-            phase: Phase::Markers,
-            active_player: 0,
-            player_hands,
-            deck,
-            dragon_tile_owner: None,
-            num_players_left: num_players,
-            num_players,
-        }
+    #[pyo3(signature = (num_players=None, seed=None, *, hand_size=3, record_history=false, record_undo=false, frame_history=0, reward_config=None))]
+    fn py_new(
+        num_players: Option<usize>,
+        seed: Option<u64>,
+        hand_size: usize,
+        record_history: bool,
+        record_undo: bool,
+        frame_history: usize,
+        reward_config: Option<RewardConfig>,
+    ) -> PyResult<Self> {
+        let config = EnvConfig {
+            hand_size,
+            record_history,
+            record_undo,
+            frame_history,
+            reward_config: reward_config.unwrap_or_default(),
+        };
+        Self::new(num_players, seed, config)
     }
 
+    /// keeps using the same rng, so a seeded env stays reproducible across episodes
+    /// clears the history and the frames of stacked_observation
     pub fn reset(&mut self) -> EnvReturn {
-        *self = Self::new(Some(self.num_players));
+        *self = Self {
+            record_history: self.record_history,
+            record_undo: self.record_undo,
+            frame_history: self.frame_history,
+            reward_config: self.reward_config,
+            ..Self::with_rng(self.num_players, self.hand_size, self.rng.clone())
+        };
+        self.push_frame();
         self.get_return(true)
     }
 
-    pub fn step_place_marker(&mut self, position_index: usize) -> EnvReturn {
+    /// position_index is one of the 0..NUM_EDGE_POSITIONS edge positions,
+    /// move_is_valid is false if it is out of range or already taken,
+    /// invalid_reason tells which
+    pub fn step_place_marker(
+        &mut self,
+        position_index: usize,
+    ) -> PyResult<EnvReturn> {
         if self.num_markers_placed >= self.num_players {
-            panic!("cannot place marker, all markers have already been placed");
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "cannot place marker, all markers have already been placed",
+            ));
         }
         let valid = self.board.place_marker(position_index);
-        if valid {
-            self.num_markers_placed += 1;
+        if !valid {
+            let reason = if position_index >= NUM_EDGE_POSITIONS {
+                InvalidMoveReason::PositionOutOfRange
+            } else {
+                InvalidMoveReason::PositionTaken
+            };
+            return Ok(self.reject_move(reason));
         }
-        self.end_turn(valid)
+        self.num_markers_placed += 1;
+        self.record(Move::PlaceMarker {
+            player: self.active_player,
+            position: position_index,
+        });
+        Ok(self.end_turn(true))
     }
 
-    pub fn step_place_tile(&mut self, tile: Tile) -> EnvReturn {
+    /// step_place_marker for every player in turn order, positions[i] is the
+    /// edge position of player i, nothing is placed if any of them is invalid
+    pub fn place_all_markers(
+        &mut self,
+        positions: Vec<usize>,
+    ) -> PyResult<EnvReturn> {
+        if self.phase != Phase::Markers || self.num_markers_placed != 0 {
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "cannot place all markers, some have already been placed",
+            ));
+        }
+        if positions.len() != self.num_players {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "expected {} marker positions, got {}",
+                self.num_players,
+                positions.len()
+            )));
+        }
+        if let Some(position) = positions.iter().find(|p| **p >= NUM_EDGE_POSITIONS)
+        {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "edge index {position} is out of range 0..{NUM_EDGE_POSITIONS}"
+            )));
+        }
+        if !positions.iter().all_unique() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "more than one marker placed at the same edge index",
+            ));
+        }
+        let mut ret = self.get_return(true);
+        for position in positions {
+            ret = self.step_place_marker(position)?;
+            debug_assert!(ret.move_is_valid);
+        }
+        Ok(ret)
+    }
+
+    /// place the markers that have not been placed yet on distinct random
+    /// edge positions, the same seed always gives the same positions
+    pub fn place_random_markers(&mut self, seed: u64) -> EnvReturn {
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+        let mut ret = self.get_return(true);
+        while self.phase == Phase::Markers
+            && self.num_markers_placed < self.num_players
+        {
+            let positions = self.legal_marker_positions();
+            let position = *positions.choose(&mut rng).unwrap();
+            ret = self
+                .step_place_marker(position)
+                .expect("not all markers have been placed");
+            debug_assert!(ret.move_is_valid);
+        }
+        ret
+    }
+
+    pub fn step_place_tile(&mut self, tile: Tile) -> PyResult<EnvReturn> {
         if self.num_markers_placed != self.num_players {
-            panic!("tried to place tile, but not all markers placed");
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "tried to place tile, but not all markers placed",
+            ));
         }
         if self.terminated() {
-            panic!("cannot place tile, game has terminated");
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "cannot place tile, game has terminated",
+            ));
+        }
+        if !self.board.can_place_tile(self.active_player) {
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+                "player {} has no empty cell on the board to place a tile on",
+                self.active_player
+            )));
         }
-        if !self.move_is_allowed(tile) {
-            println!("disallowed");
-            return self.end_turn(false);
+        if let Some(reason) = self.invalid_reason(tile) {
+            return Ok(self.reject_move(reason));
         }
 
-        self.handle_collisions(tile); // before place_tile
-        if self.terminated() {
-            return self.end_turn(true);
+        if self.record_undo {
+            self.undo_stack.push(self.undo_record(false));
         }
+        self.record(Move::PlaceTile {
+            player: self.active_player,
+            tile,
+        });
+        let was_alive = self.board.markers.iter().map(Option::is_some).collect_vec();
+        let path_lengths = self.path_lengths();
+        // the tile is placed even if the active player collides, it has to
+        // leave the hand before eliminating the player returns it to the deck
         self.place_tile(tile);
-        self.move_markers(); // also eliminates players
+        self.handle_collisions(tile); // before the markers move
         if !self.terminated() {
-            self.draw_tiles();
+            self.move_markers(); // also eliminates players
+            if !self.terminated() {
+                self.draw_tiles();
+            }
         }
-        self.end_turn(true)
+        self.push_frame();
+        let mut ret = self.end_turn(true);
+        ret.rewards = self.rewards(&was_alive, &path_lengths);
+        Ok(ret)
     }
 
-    pub fn get_deck(&self) -> Vec<Tile> {
-        self.deck.clone()
+    /// step_place_tile with the active player's hand tile at hand_index
+    /// rotated by rotation quarter turns, for an action space of (slot, rotation)
+    /// both indices are checked, then the move is validated like step_place_tile
+    pub fn step_place_tile_action(
+        &mut self,
+        hand_index: usize,
+        rotation: usize,
+    ) -> PyResult<EnvReturn> {
+        if rotation >= 4 {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "rotation has to be in 0..4, got {rotation}"
+            )));
+        }
+        let hand = self.player_hands.get(self.active_player);
+        let Some(tile) = hand.and_then(|hand| hand.get(hand_index)) else {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "hand_index {hand_index} is out of range for a hand of {} tiles",
+                hand.map_or(0, Vec::len)
+            )));
+        };
+        self.step_place_tile(tile.rotated(rotation))
     }
 
-    pub fn set_top_tile(&mut self, tile: Tile) {
-        let idx = self.deck.iter().position(|t| *t == tile);
-        if idx.is_none() {
-            panic!("tile not found in deck");
+    /// ends the turn of the active player without placing a tile
+    /// only allowed in the tile phase if the player has no tiles,
+    /// which happens when the deck runs out while others still hold tiles
+    pub fn step_pass(&mut self) -> PyResult<EnvReturn> {
+        if !self.can_pass() {
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "cannot pass, the player has tiles to place or it is not the tile phase",
+            ));
         }
-        let idx = idx.unwrap();
-        self.deck.swap_remove(idx);
-        self.deck.push(tile);
+        if self.record_undo {
+            self.undo_stack.push(self.undo_record(true));
+        }
+        self.record(Move::Pass {
+            player: self.active_player,
+        });
+        Ok(self.end_turn(true))
     }
 
-    pub fn clone(&self) -> Self {
-        Self {
-            board: self.board.clone(),
+    /// every distinct rotated hand tile the active player may place
+    /// includes suicide moves only if all moves are suicide
+    /// empty outside of the tile phase
+    pub fn legal_actions(&self) -> Vec<Tile> {
+        if self.phase != Phase::Tiles || self.terminated() {
+            return vec![];
+        }
+        self.all_rotated_tiles_of(self.active_player)
+            .filter(|tile| self.move_is_allowed(*tile))
+            .sorted_unstable()
+            .dedup()
+            .collect()
+    }
+
+    /// indices of the edge positions where a marker can still be placed
+    /// empty outside of the marker phase
+    pub fn legal_marker_positions(&self) -> Vec<usize> {
+        if self.phase != Phase::Markers {
+            return vec![];
+        }
+        (0..NUM_EDGE_POSITIONS)
+            .filter(|idx| {
+                !self
+                    .board
+                    .position_is_taken(MarkerPosition::from_index(*idx))
+            })
+            .collect()
+    }
+
+    /// the Board.tile_tensor of the last k boards in a [k, BOARD_SIZE, BOARD_SIZE, 36]
+    /// tensor, oldest first, so frame k - 1 is the current board
+    /// a frame is kept at the start and after every tile placement,
+    /// frames before the start of the game are all zeros
+    /// undo drops the last frame without bringing back older ones
+    pub fn stacked_observation(&self, k: usize) -> PyResult<PyTensor> {
+        if k > self.frame_history {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "cannot stack {k} frames, the env keeps {}",
+                self.frame_history
+            )));
+        }
+        let frame_size = BOARD_SIZE * BOARD_SIZE * 36;
+        let num_padding = k.saturating_sub(self.frames.len());
+        let mut stacked = vec![0i8; num_padding * frame_size];
+        for frame in self.frames.iter().skip(self.frames.len().saturating_sub(k)) {
+            stacked.extend_from_slice(frame);
+        }
+        let size = BOARD_SIZE as i64;
+        Ok(PyTensor(
+            Tensor::from_slice(&stacked).view([k as i64, size, size, 36]),
+        ))
+    }
+
+    /// mask over the hand_size * 4 tile actions,
+    /// action index is hand_index * 4 + rotation
+    /// 1 if placing the hand tile with that rotation is legal, 0 otherwise
+    /// if several rotations give the same tile only the lowest index is set
+    pub fn action_mask_tensor(&self) -> PyTensor {
+        let legal_actions = self.legal_actions();
+        let mut marked = vec![];
+        let mut mask = vec![0i8; self.hand_size * 4];
+        for (action, tile) in
+            self.all_rotated_tiles_of(self.active_player).enumerate()
+        {
+            if legal_actions.contains(&tile) && !marked.contains(&tile) {
+                mask[action] = 1;
+                marked.push(tile);
+            }
+        }
+        PyTensor(Tensor::from_slice(&mask))
+    }
+
+    /// reverts the last valid step_place_tile or step_pass
+    /// only recorded if record_undo is set, then every move since the
+    /// last reset can be undone
+    pub fn undo(&mut self) -> PyResult<()> {
+        if !self.record_undo {
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "cannot undo, the env was made with record_undo=False",
+            ));
+        }
+        let record = self.undo_stack.pop().ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "no tile placement or pass to undo",
+            )
+        })?;
+        if let Some((tile_coords, graph_patch)) = record.placement {
+            self.board.remove_tile(tile_coords, graph_patch);
+            self.frames.pop_back();
+        }
+        self.board.markers = record.markers;
+        self.player_hands = record.player_hands;
+        self.deck = record.deck;
+        self.dragon_tile_owner = record.dragon_tile_owner;
+        self.num_players_left = record.num_players_left;
+        self.active_player = record.active_player;
+        self.rng = record.rng;
+        if self.record_history {
+            // every record was made together with the last move in history
+            self.history.pop();
+        }
+        Ok(())
+    }
+
+    /// copy of the state that can be restored later without cloning the graph
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            tiles: self.board.tiles,
+            markers: self.board.markers.clone(),
             num_markers_placed: self.num_markers_placed,
             phase: self.phase,
             active_player: self.active_player,
@@ -97,22 +389,479 @@ impl TsuroEnv {
             deck: self.deck.clone(),
             dragon_tile_owner: self.dragon_tile_owner,
             num_players_left: self.num_players_left,
-            num_players: self.num_players,
+            rng: self.rng.clone(),
+            undo_depth: self.undo_stack.len(),
+            undo_top: self.undo_stack.last().map(|record| record.id),
+            history_len: self.history.len(),
+        }
+    }
+
+    /// go back to the state the snapshot was taken in
+    /// the graph is reverted with undo, so the snapshot has to be taken
+    /// from this env, in a state that lead to the current one
+    /// without record_undo only snapshots with the current tiles can be restored
+    pub fn restore(&mut self, snapshot: &Snapshot) -> PyResult<()> {
+        if !self.record_undo && snapshot.tiles != self.board.tiles {
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "cannot restore past a tile placement, the env was made with record_undo=False",
+            ));
+        }
+        let tiles_match = zip(
+            self.board.tiles.as_flattened(),
+            snapshot.tiles.as_flattened(),
+        )
+        .all(|(current, snapshot)| snapshot.is_none() || current == snapshot);
+        // the record below the snapshot is still on the stack only if no move
+        // before the snapshot was undone since, in this env or a clone of it
+        let same_line = snapshot.undo_depth <= self.undo_stack.len()
+            && self.undo_stack[..snapshot.undo_depth]
+                .last()
+                .map(|record| record.id)
+                == snapshot.undo_top;
+        if !same_line || !tiles_match {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "snapshot was not taken from an earlier state of this env",
+            ));
+        }
+        while self.undo_stack.len() > snapshot.undo_depth {
+            self.undo()?;
+        }
+        self.board.tiles = snapshot.tiles;
+        self.board.markers = snapshot.markers.clone();
+        self.num_markers_placed = snapshot.num_markers_placed;
+        self.phase = snapshot.phase;
+        self.active_player = snapshot.active_player;
+        self.player_hands = snapshot.player_hands.clone();
+        self.deck = snapshot.deck.clone();
+        self.dragon_tile_owner = snapshot.dragon_tile_owner;
+        self.num_players_left = snapshot.num_players_left;
+        self.rng = snapshot.rng.clone();
+        self.history.truncate(snapshot.history_len);
+        Ok(())
+    }
+
+    /// every player's hand and the deck, for replay tools and debugging
+    /// this reveals hidden information, so do not give it to agents
+    pub fn spectator_view(&self) -> FullView {
+        FullView {
+            board: self.board.clone(),
+            player_hands: self.player_hands.clone(),
+            deck: self.deck.clone(),
+            active_player: self.active_player,
+            dragon_tile_owner: self.dragon_tile_owner,
+        }
+    }
+
+    /// replay moves in a new env, the seed has to be the one the moves were
+    /// made with so the same tiles are drawn
+    /// the returned env records its history
+    #[staticmethod]
+    #[pyo3(signature = (num_players, seed, moves, hand_size=3))]
+    pub fn from_history(
+        num_players: usize,
+        seed: u64,
+        moves: Vec<Move>,
+        hand_size: usize,
+    ) -> PyResult<TsuroEnv> {
+        let mut env = TsuroEnv::new(
+            Some(num_players),
+            Some(seed),
+            EnvConfig {
+                hand_size,
+                record_history: true,
+                ..Default::default()
+            },
+        )?;
+        for (idx, m) in moves.into_iter().enumerate() {
+            let (player, legal) = match m {
+                Move::PlaceMarker { player, position } => {
+                    (player, env.legal_marker_positions().contains(&position))
+                }
+                Move::PlaceTile { player, tile } => {
+                    (player, env.legal_actions().contains(&tile))
+                }
+                Move::Pass { player } => (player, env.can_pass()),
+            };
+            if player != env.active_player || !legal {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    format!("move {idx} ({m:?}) is not legal"),
+                ));
+            }
+            match m {
+                Move::PlaceMarker { position, .. } => {
+                    env.step_place_marker(position)?
+                }
+                Move::PlaceTile { tile, .. } => env.step_place_tile(tile)?,
+                Move::Pass { .. } => env.step_pass()?,
+            };
+        }
+        Ok(env)
+    }
+
+    /// valid moves since the last reset, empty unless record_history was set
+    pub fn history(&self) -> Vec<Move> {
+        self.history.clone()
+    }
+
+    pub fn get_deck(&self) -> Vec<Tile> {
+        self.deck.clone()
+    }
+
+    /// wether the deck, the hands and the placed tiles together are exactly ALL_TILES,
+    /// compared up to rotation
+    /// false after set_deck left some of the free tiles out
+    pub fn all_tiles_accounted(&self) -> bool {
+        let in_game = self
+            .deck
+            .iter()
+            .chain(self.player_hands.iter().flatten())
+            .chain(self.board.tiles.as_flattened().iter().flatten())
+            .map(Tile::canonical)
+            .sorted_unstable();
+        let all_tiles = ALL_TILES.iter().map(Tile::canonical).sorted_unstable();
+        in_game.eq(all_tiles)
+    }
+
+    pub fn num_players_left(&self) -> usize {
+        self.num_players_left
+    }
+
+    /// players that have not been eliminated,
+    /// every player until all markers are placed
+    pub fn remaining_players(&self) -> Vec<usize> {
+        if self.num_markers_placed != self.num_players {
+            return (0..self.num_players).collect();
+        }
+        self.board
+            .markers
+            .iter()
+            .enumerate()
+            .filter_map(|(player, marker)| marker.map(|_| player))
+            .collect()
+    }
+
+    /// players that survived once the game has terminated,
+    /// several if the tiles ran out with more than one player left
+    /// empty if the last players were eliminated by the same move, which is a draw
+    pub fn winners(&self) -> PyResult<Vec<usize>> {
+        if self.num_players == 0 || !self.terminated() {
+            return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "the game has not terminated yet",
+            ));
+        }
+        Ok(self.remaining_players())
+    }
+
+    /// None while the game is running
+    pub fn termination_reason(&self) -> Option<TerminationReason> {
+        if self.num_players_left < 2 {
+            Some(TerminationReason::Elimination)
+        } else if self.player_hands.iter().all(|hand| hand.is_empty())
+            && self.deck.is_empty()
+        {
+            Some(TerminationReason::TilesExhausted)
+        } else {
+            None
+        }
+    }
+
+    pub fn deck_size(&self) -> usize {
+        self.deck.len()
+    }
+
+    /// for each tile of ALL_TILES, how many copies the active player has not seen,
+    /// these are in the deck or in the hands of the other players
+    /// every tile is unique, so the counts are 0 or 1
+    pub fn remaining_tile_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; ALL_TILES.len()];
+        let other_hands = self
+            .player_hands
+            .iter()
+            .enumerate()
+            .filter(|(player, _hand)| *player != self.active_player)
+            .flat_map(|(_player, hand)| hand);
+        for tile in self.deck.iter().chain(other_hands) {
+            let index = tile
+                .index()
+                .expect("every tile should be a rotation of a tile in ALL_TILES");
+            counts[index] += 1;
+        }
+        counts
+    }
+
+    /// count of each tile of ALL_TILES in the deck, shape [35], not its order
+    /// together with a hand this tells which tiles the other players hold,
+    /// remaining_tile_counts is what a player can know
+    pub fn deck_tensor(&self) -> PyTensor {
+        let mut counts = vec![0i8; ALL_TILES.len()];
+        for tile in &self.deck {
+            let index = tile
+                .index()
+                .expect("every tile should be a rotation of a tile in ALL_TILES");
+            counts[index] += 1;
+        }
+        PyTensor(Tensor::from_slice(&counts))
+    }
+
+    /// input for the marker phase, when the board has no tiles yet
+    /// shape [NUM_EDGE_POSITIONS + 1], entry i is 1 if edge position i is taken,
+    /// the last entry is the number of markers still to be placed
+    pub fn marker_phase_observation(&self) -> PyTensor {
+        let mut observation = (0..NUM_EDGE_POSITIONS)
+            .map(|idx| {
+                let position = MarkerPosition::from_index(idx);
+                self.board.position_is_taken(position) as i8
+            })
+            .collect_vec();
+        observation.push((self.num_players - self.num_markers_placed) as i8);
+        PyTensor(Tensor::from_slice(&observation))
+    }
+
+    pub fn set_top_tile(&mut self, tile: Tile) -> PyResult<()> {
+        let idx = self.deck.iter().position(|t| *t == tile).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>("tile not found in deck")
+        })?;
+        self.deck.swap_remove(idx);
+        self.deck.push(tile);
+        Ok(())
+    }
+
+    /// replaces the deck, tiles are drawn from the end like get_deck
+    /// the tiles have to be from ALL_TILES and not be in a hand or on the board
+    pub fn set_deck(&mut self, deck: Vec<Tile>) -> PyResult<()> {
+        let in_play = self
+            .player_hands
+            .iter()
+            .flatten()
+            .chain(self.board.tiles.as_flattened().iter().flatten())
+            .map(Tile::canonical)
+            .collect_vec();
+        let all_tiles = ALL_TILES.map(|tile| tile.canonical());
+        let canonical_deck = deck.iter().map(Tile::canonical).collect_vec();
+        for tile in &canonical_deck {
+            if !all_tiles.contains(tile) || in_play.contains(tile) {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    format!("{tile:?} is not available for the deck"),
+                ));
+            }
+        }
+        if !canonical_deck.iter().all_unique() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "deck contains the same tile twice",
+            ));
+        }
+        self.deck = deck;
+        Ok(())
+    }
+
+    /// winners after playing uniformly random legal moves for every player
+    /// on a copy of the env until the game terminates, self is not changed
+    /// the moves only depend on seed and the state of the env
+    pub fn rollout_random(&self, seed: u64) -> Vec<usize> {
+        if self.num_players == 0 {
+            return vec![];
+        }
+        let mut env = self.clone();
+        env.record_history = false;
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+        while !env.terminated() {
+            let ret = if env.phase == Phase::Markers {
+                let position =
+                    *env.legal_marker_positions().choose(&mut rng).unwrap();
+                env.step_place_marker(position)
+            } else if let Some(tile) = env.legal_actions().choose(&mut rng) {
+                env.step_place_tile(*tile)
+            } else {
+                env.step_pass()
+            };
+            debug_assert!(ret.is_ok_and(|ret| ret.move_is_valid));
+        }
+        env.remaining_players()
+    }
+
+    /// the same as copy.copy(env), kept for existing callers
+    #[pyo3(name = "clone")]
+    fn py_clone(&self) -> Self {
+        self.clone()
+    }
+}
+
+#[pymethods]
+impl RewardConfig {
+    /// the defaults are the sparse win/loss rewards
+    #[new]
+    #[pyo3(signature = (survival_bonus=0.0, path_length_weight=0.0, elimination_penalty=1.0, win_bonus=1.0))]
+    pub fn new(
+        survival_bonus: f32,
+        path_length_weight: f32,
+        elimination_penalty: f32,
+        win_bonus: f32,
+    ) -> Self {
+        Self {
+            survival_bonus,
+            path_length_weight,
+            elimination_penalty,
+            win_bonus,
         }
     }
 }
 
 impl TsuroEnv {
+    /// the rust side of the python constructor, num_players None gives an
+    /// empty env like TsuroEnv.__new__()
+    pub fn new(
+        num_players: Option<usize>,
+        seed: Option<u64>,
+        config: EnvConfig,
+    ) -> PyResult<Self> {
+        if num_players.is_none() {
+            return Ok(Self::default());
+        }
+        let num_players = num_players.unwrap();
+        if !PLAYER_RANGE.contains(&num_players) {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "a game needs {} to {} players, got {num_players}",
+                PLAYER_RANGE.start(),
+                PLAYER_RANGE.end()
+            )));
+        }
+        if config.hand_size == 0 {
+            // a player without tiles can only pass, so the game would never end
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "hand_size has to be at least 1",
+            ));
+        }
+        if config.hand_size * num_players > ALL_TILES.len() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "cannot deal {} tiles to each of {num_players} players",
+                config.hand_size
+            )));
+        }
+        let rng = match seed {
+            Some(seed) => ChaCha12Rng::seed_from_u64(seed),
+            None => ChaCha12Rng::from_rng(&mut rng()),
+        };
+        let mut env = Self {
+            record_history: config.record_history,
+            record_undo: config.record_undo,
+            frame_history: config.frame_history,
+            reward_config: config.reward_config,
+            ..Self::with_rng(num_players, config.hand_size, rng)
+        };
+        env.push_frame();
+        Ok(env)
+    }
+
+    fn with_rng(num_players: usize, hand_size: usize, mut rng: ChaCha12Rng) -> Self {
+        let mut deck = ALL_TILES.into_iter().collect_vec();
+        deck.shuffle(&mut rng);
+        let mut player_hands = vec![Vec::new(); num_players];
+        for hand in player_hands.iter_mut() {
+            hand.append(&mut deck.split_off(deck.len() - hand_size));
+        }
+        TsuroEnv {
+            board: Board::new(),
+            num_markers_placed: 0,
+            phase: Phase::Markers,
+            active_player: 0,
+            player_hands,
+            deck,
+            dragon_tile_owner: None,
+            num_players_left: num_players,
+            num_players,
+            hand_size,
+            rng,
+            record_undo: false,
+            undo_stack: vec![],
+            record_history: false,
+            history: vec![],
+            frame_history: 0,
+            frames: VecDeque::new(),
+            reward_config: RewardConfig::default(),
+        }
+    }
+
+    /// all_tiles_accounted except that tiles may be missing, set_deck allows that
+    fn no_tile_duplicated(&self) -> bool {
+        let all_tiles = ALL_TILES.map(|tile| tile.canonical());
+        let in_game = self
+            .deck
+            .iter()
+            .chain(self.player_hands.iter().flatten())
+            .chain(self.board.tiles.as_flattened().iter().flatten())
+            .map(Tile::canonical)
+            .collect_vec();
+        in_game.iter().all(|tile| all_tiles.contains(tile))
+            && in_game.iter().all_unique()
+    }
+
+    fn can_pass(&self) -> bool {
+        self.phase == Phase::Tiles
+            && !self.terminated()
+            && self.player_hands[self.active_player].is_empty()
+    }
+
+    /// keep the current tiles for stacked_observation
+    fn push_frame(&mut self) {
+        if self.frame_history == 0 {
+            return;
+        }
+        if self.frames.len() == self.frame_history {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(self.board.tile_one_hot());
+    }
+
+    fn record(&mut self, m: Move) {
+        if self.record_history {
+            self.history.push(m);
+        }
+    }
+
+    /// has to be called before the tile is placed or the player passes
+    fn undo_record(&self, is_pass: bool) -> UndoRecord {
+        let placement = (!is_pass).then(|| {
+            let tile_coords = self.board.next_tile_of_player(self.active_player);
+            (tile_coords, self.board.graph.patch_for(tile_coords))
+        });
+        UndoRecord {
+            id: NEXT_UNDO_ID.fetch_add(1, Ordering::Relaxed),
+            placement,
+            markers: self.board.markers.clone(),
+            player_hands: self.player_hands.clone(),
+            deck: self.deck.clone(),
+            dragon_tile_owner: self.dragon_tile_owner,
+            num_players_left: self.num_players_left,
+            active_player: self.active_player,
+            rng: self.rng.clone(),
+        }
+    }
+
     fn end_turn(&mut self, move_is_valid: bool) -> EnvReturn {
+        let acted_player = self.active_player;
         if move_is_valid && !self.terminated() {
-            self.active_player = self.player_after(self.active_player);
+            // a running game in the tile phase always has another player left
+            if let Some(next) = self.player_after(self.active_player) {
+                self.active_player = next;
+            }
         }
         if self.phase == Phase::Markers
             && self.num_markers_placed == self.num_players
         {
             self.phase = Phase::Tiles;
         }
-        self.get_return(move_is_valid)
+        debug_assert!(self.no_tile_duplicated());
+        EnvReturn {
+            acted_player,
+            ..self.get_return(move_is_valid)
+        }
+    }
+
+    fn reject_move(&mut self, reason: InvalidMoveReason) -> EnvReturn {
+        EnvReturn {
+            invalid_reason: Some(reason),
+            ..self.end_turn(false)
+        }
     }
 
     fn place_tile(&mut self, tile: Tile) {
@@ -126,12 +875,10 @@ impl TsuroEnv {
     }
 
     fn terminated(&self) -> bool {
-        self.num_players_left < 2
-            || (self.player_hands.iter().all(|hand| hand.is_empty())
-                && self.deck.is_empty())
+        self.termination_reason().is_some()
     }
 
-    /// eliminate players that collide
+    /// eliminate players that collide on the tile in front of the active player
     fn handle_collisions(&mut self, tile: Tile) {
         let colliding_players = self.board.find_collisions(
             tile,
@@ -151,24 +898,34 @@ impl TsuroEnv {
         }
     }
 
+    /// deal one tile at a time in turn order, starting with the dragon tile owner
+    /// if there is one, otherwise the active player
+    /// players with full hands are skipped and eliminated players never draw
+    /// if the deck runs out, the first player left without a tile gets the dragon tile
     fn draw_tiles(&mut self) {
-        let mut current = if self.dragon_tile_owner.is_some() {
-            self.dragon_tile_owner.unwrap()
-        } else {
-            self.active_player
-        };
-
-        if !self.deck.is_empty() && self.dragon_tile_owner.is_some() {
-            self.dragon_tile_owner = None;
-        }
-
-        while !self.deck.is_empty() && self.player_hands[current].len() < 3 {
-            self.player_hands[current].push(self.deck.pop().unwrap());
-            current = self.player_after(current);
-        }
+        let start = self.dragon_tile_owner.unwrap_or(self.active_player);
+        self.dragon_tile_owner = None;
+        let players = (0..self.num_players)
+            .map(|offset| (start + offset) % self.num_players)
+            .filter(|player| self.board.markers[*player].is_some())
+            .collect_vec();
 
-        if self.dragon_tile_owner.is_none() && self.player_hands[current].len() < 3 {
-            self.dragon_tile_owner = Some(current);
+        loop {
+            let mut any_drawn = false;
+            for &player in &players {
+                if self.player_hands[player].len() >= self.hand_size {
+                    continue;
+                }
+                let Some(tile) = self.deck.pop() else {
+                    self.dragon_tile_owner = Some(player);
+                    return;
+                };
+                self.player_hands[player].push(tile);
+                any_drawn = true;
+            }
+            if !any_drawn {
+                return; // all hands are full
+            }
         }
     }
 }
@@ -182,51 +939,69 @@ impl TsuroEnv {
         }
     }
 
-    fn player_after(&self, player: usize) -> usize {
-        let mut ret = player;
-        ret += 1;
-        ret %= self.num_players;
-
+    /// next player in turn order that has not been eliminated,
+    /// None if every other player has been
+    fn player_after(&self, player: usize) -> Option<usize> {
+        let mut others =
+            (1..self.num_players).map(|offset| (player + offset) % self.num_players);
         if self.phase == Phase::Markers {
-            return ret;
-        }
-
-        let mut num_iterations = 0;
-        while self.board.markers[ret].is_none() {
-            if num_iterations > self.num_players {
-                panic!("all players eliminated");
-            }
-            ret += 1;
-            ret %= self.num_players;
-            num_iterations += 1;
+            return others.next();
         }
-        if ret == player {
-            panic!("only one player left");
-        }
-        ret
+        others.find(|next| self.board.markers[*next].is_some())
     }
 
-    fn get_return(&self, move_is_valid: bool) -> EnvReturn {
-        let remaining_players = if self.num_markers_placed == self.num_players {
-            self.board
-                .markers
-                .iter()
-                .enumerate()
-                .filter_map(|(player, marker)| marker.map(|_| player))
-                .collect_vec()
-        } else {
-            (0..self.num_players).collect()
-        };
+    pub(crate) fn get_return(&self, move_is_valid: bool) -> EnvReturn {
+        let remaining_players = self.remaining_players();
         EnvReturn {
             view: self.view_of(self.active_player),
             terminated: self.terminated(),
+            termination_reason: self.termination_reason(),
+            acted_player: self.active_player,
             active_player: self.active_player,
+            legal_actions: self.legal_actions(),
             phase: self.phase,
             remaining_players,
             move_is_valid,
+            invalid_reason: None,
+            rewards: vec![0.0; self.num_players],
+            dragon_tile_owner: self.dragon_tile_owner,
         }
     }
 
+    /// the terms of reward_config for the players alive in was_alive,
+    /// path_lengths are the Board::path_length of each player at the same time
+    fn rewards(&self, was_alive: &[bool], path_lengths: &[usize]) -> Vec<f32> {
+        let config = &self.reward_config;
+        let win_bonus = if self.terminated() {
+            config.win_bonus
+        } else {
+            0.0
+        };
+        (0..self.num_players)
+            .map(|player| {
+                if self.board.markers[player].is_none() {
+                    let eliminated_now = was_alive[player];
+                    return if eliminated_now {
+                        -config.elimination_penalty
+                    } else {
+                        0.0
+                    };
+                }
+                let growth = self.board.path_length(player) as f32
+                    - path_lengths[player] as f32;
+                config.survival_bonus
+                    + config.path_length_weight * growth
+                    + win_bonus
+            })
+            .collect()
+    }
+
+    fn path_lengths(&self) -> Vec<usize> {
+        (0..self.num_players)
+            .map(|player| self.board.path_length(player))
+            .collect()
+    }
+
     fn all_rotated_tiles_of<'a>(
         &'a self,
         player: usize,
@@ -239,9 +1014,14 @@ impl TsuroEnv {
     }
 
     fn move_is_allowed(&self, tile: Tile) -> bool {
+        self.invalid_reason(tile).is_none()
+    }
+
+    /// why the active player may not place the tile, None if they may
+    fn invalid_reason(&self, tile: Tile) -> Option<InvalidMoveReason> {
         let tile_idx = self.active_player_hand_find_tile(tile);
         if tile_idx.is_none() {
-            return false; // tile is not in the player's hand
+            return Some(InvalidMoveReason::NotInHand);
         }
         let is_suicide = self.board.move_is_suicide(tile, self.active_player);
         if is_suicide {
@@ -250,24 +1030,1140 @@ impl TsuroEnv {
             if all_possible_moves
                 .any(|tile| !self.board.move_is_suicide(tile, self.active_player))
             {
-                return false;
+                return Some(InvalidMoveReason::SuicideNotForced);
             }
             // All moves are suicide
-            return true;
+            return None;
         }
-        true
+        None
     }
 
+    /// index in the active player's hand of a tile that rotates into tile
     fn active_player_hand_find_tile(&self, tile: Tile) -> Option<usize> {
-        let mut hand = self.all_rotated_tiles_of(self.active_player);
-        let idx = hand.position(|t| t == tile);
-        idx.map(|idx| idx / 4)
+        self.player_hands[self.active_player]
+            .iter()
+            .enumerate()
+            .cartesian_product(0..4)
+            .find(|((_hand_idx, hand_tile), rotation)| {
+                hand_tile.rotated(*rotation) == tile
+            })
+            .map(|((hand_idx, _hand_tile), _rotation)| hand_idx)
     }
 
     fn eliminate_player(&mut self, player: usize) {
         self.board.eliminate_player(player);
         self.deck.append(&mut self.player_hands[player]);
-        self.deck.shuffle(&mut rng());
+        self.deck.shuffle(&mut self.rng);
         self.num_players_left -= 1;
     }
 }
+
+#[test]
+fn test_seeded_env_is_reproducible() {
+    let env = TsuroEnv::new(Some(4), Some(42), EnvConfig::default()).unwrap();
+    assert_eq!(
+        env,
+        TsuroEnv::new(Some(4), Some(42), EnvConfig::default()).unwrap()
+    );
+    assert_ne!(
+        env.deck,
+        TsuroEnv::new(Some(4), Some(43), EnvConfig::default())
+            .unwrap()
+            .deck
+    );
+}
+
+#[test]
+fn test_serialized_env_keeps_rng_state() {
+    use bincode::config;
+    use bincode::serde::{decode_from_slice, encode_to_vec};
+
+    let mut env = TsuroEnv::new(Some(2), Some(7), EnvConfig::default()).unwrap();
+    let bytes = encode_to_vec(&env, config::standard()).unwrap();
+    let (mut restored, _): (TsuroEnv, _) =
+        decode_from_slice(&bytes, config::standard()).unwrap();
+    env.reset();
+    restored.reset();
+    assert_eq!(env, restored);
+}
+
+#[test]
+fn test_legal_actions() {
+    let mut env = TsuroEnv::new(Some(2), Some(0), EnvConfig::default()).unwrap();
+    assert!(env.legal_actions().is_empty());
+    env.step_place_marker(0).unwrap();
+    let positions = env.legal_marker_positions();
+    assert_eq!(positions.len(), NUM_EDGE_POSITIONS - 1);
+    assert!(!positions.contains(&0));
+    env.step_place_marker(20).unwrap();
+
+    assert!(env.legal_marker_positions().is_empty());
+    let actions = env.legal_actions();
+    assert!(!actions.is_empty());
+    assert!(actions.iter().all(|tile| env.move_is_allowed(*tile)));
+    assert!(actions.iter().all_unique());
+    assert!(env
+        .all_rotated_tiles_of(0)
+        .filter(|tile| !actions.contains(tile))
+        .all(|tile| !env.move_is_allowed(tile)));
+}
+
+#[test]
+fn test_action_mask_tensor() {
+    let mut env = TsuroEnv::new(Some(2), Some(1), EnvConfig::default()).unwrap();
+    env.step_place_marker(4).unwrap();
+    env.step_place_marker(30).unwrap();
+    // a symmetric tile has duplicate rotations but is a single legal action
+    env.player_hands[0][0] = Tile::from_code("12-34-56-78").unwrap();
+    let mask = env.action_mask_tensor();
+    assert_eq!(mask.size(), [12]);
+    assert_eq!(
+        mask.sum(tch::Kind::Int64).int64_value(&[]) as usize,
+        env.legal_actions().len()
+    );
+}
+
+#[test]
+fn test_hand_size() {
+    let env = TsuroEnv::new(
+        Some(3),
+        Some(0),
+        EnvConfig {
+            hand_size: 5,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(env.player_hands.iter().all(|hand| hand.len() == 5));
+    assert_eq!(env.deck.len(), 35 - 3 * 5);
+    assert!(TsuroEnv::new(
+        Some(8),
+        Some(0),
+        EnvConfig {
+            hand_size: 5,
+            ..Default::default()
+        }
+    )
+    .is_err());
+    assert!(TsuroEnv::new(
+        Some(2),
+        Some(0),
+        EnvConfig {
+            hand_size: 0,
+            ..Default::default()
+        }
+    )
+    .is_err());
+}
+
+#[test]
+fn test_num_players_range() {
+    for num_players in [0, 1, 9, 35] {
+        assert!(
+            TsuroEnv::new(Some(num_players), Some(0), EnvConfig::default()).is_err()
+        );
+    }
+    for num_players in 2..=8 {
+        let env =
+            TsuroEnv::new(Some(num_players), Some(0), EnvConfig::default()).unwrap();
+        assert_eq!(env.num_players, num_players);
+    }
+}
+
+#[test]
+fn test_rewards() {
+    let mut env = TsuroEnv::new(Some(3), Some(3), EnvConfig::default()).unwrap();
+    for position in [0, 10, 20] {
+        let ret = env.step_place_marker(position).unwrap();
+        assert_eq!(ret.rewards, vec![0.0; 3]);
+    }
+    let mut total = [0.0; 3];
+    loop {
+        let tile = env.legal_actions()[0];
+        let ret = env.step_place_tile(tile).unwrap();
+        for (total, reward) in total.iter_mut().zip(&ret.rewards) {
+            *total += reward;
+        }
+        if ret.terminated {
+            break;
+        }
+    }
+    for (player, marker) in env.board.markers.iter().enumerate() {
+        let expected = if marker.is_some() { 1.0 } else { -1.0 };
+        assert_eq!(total[player], expected);
+    }
+}
+
+#[test]
+fn test_shaped_rewards() {
+    let config = RewardConfig::new(0.1, 0.5, 2.0, 10.0);
+    let mut env = TsuroEnv::new(
+        Some(3),
+        Some(3),
+        EnvConfig {
+            reward_config: config,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for position in [0, 10, 20] {
+        let ret = env.step_place_marker(position).unwrap();
+        assert_eq!(ret.rewards, vec![0.0; 3]);
+    }
+    loop {
+        let was_alive = env.board.markers.iter().map(Option::is_some).collect_vec();
+        let path_lengths = env.path_lengths();
+        let ret = env.step_place_tile(env.legal_actions()[0]).unwrap();
+        for player in 0..3 {
+            let expected = match (env.board.markers[player], was_alive[player]) {
+                (None, true) => -2.0,
+                (None, false) => 0.0,
+                (Some(_), _) => {
+                    let growth =
+                        env.board.path_length(player) - path_lengths[player];
+                    let win_bonus = if ret.terminated { 10.0 } else { 0.0 };
+                    0.1 + 0.5 * growth as f32 + win_bonus
+                }
+            };
+            assert_eq!(ret.rewards[player], expected);
+        }
+        if ret.terminated {
+            break;
+        }
+    }
+    env.reset();
+    assert_eq!(env.reward_config, config);
+}
+
+#[test]
+fn test_survival_rewards() {
+    let config = RewardConfig::new(1.0, 0.0, 0.0, 0.0);
+    let mut env = TsuroEnv::new(
+        Some(2),
+        Some(0),
+        EnvConfig {
+            reward_config: config,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    env.place_all_markers(vec![0, 24]).unwrap();
+    let mut total = [0.0; 2];
+    let mut turns_survived = [0.0; 2];
+    while !env.legal_actions().is_empty() {
+        let ret = env.step_place_tile(env.legal_actions()[0]).unwrap();
+        for player in 0..2 {
+            total[player] += ret.rewards[player];
+            if env.board.markers[player].is_some() {
+                turns_survived[player] += 1.0;
+            }
+        }
+    }
+    assert_eq!(total, turns_survived);
+    assert!(total.iter().any(|total| *total > 0.0));
+}
+
+#[test]
+fn test_undo() {
+    let mut env = TsuroEnv::new(
+        Some(3),
+        Some(5),
+        EnvConfig {
+            record_undo: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for position in [1, 17, 33] {
+        env.step_place_marker(position).unwrap();
+    }
+    assert!(env.undo().is_err());
+    let mut history = vec![];
+    while !env.terminated() {
+        history.push(env.clone());
+        let tile = env.legal_actions()[0];
+        env.step_place_tile(tile).unwrap();
+    }
+    assert!(env.num_players_left < 3);
+    while let Some(previous) = history.pop() {
+        env.undo().unwrap();
+        assert_eq!(env, previous);
+    }
+    assert!(env.undo().is_err());
+}
+
+#[cfg(test)]
+fn play_until_terminated(env: &mut TsuroEnv) {
+    while !env.terminated() {
+        let tile = env.legal_actions()[0];
+        env.step_place_tile(tile).unwrap();
+    }
+}
+
+#[test]
+fn test_snapshot_restore() {
+    let mut env = TsuroEnv::new(
+        Some(3),
+        Some(9),
+        EnvConfig {
+            record_undo: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    env.step_place_marker(2).unwrap();
+    let marker_phase = (env.clone(), env.snapshot());
+    env.step_place_marker(18).unwrap();
+    env.step_place_marker(34).unwrap();
+    for _ in 0..4 {
+        let tile = env.legal_actions()[0];
+        env.step_place_tile(tile).unwrap();
+    }
+    let tile_phase = (env.clone(), env.snapshot());
+
+    play_until_terminated(&mut env);
+    env.restore(&tile_phase.1).unwrap();
+    assert_eq!(env, tile_phase.0);
+    play_until_terminated(&mut env);
+    env.restore(&marker_phase.1).unwrap();
+    assert_eq!(env, marker_phase.0);
+    // tile_phase is not an earlier state anymore
+    assert!(env.restore(&tile_phase.1).is_err());
+}
+
+#[test]
+fn test_restore_rejects_other_lines() {
+    let mut env = TsuroEnv::new(
+        Some(2),
+        Some(0),
+        EnvConfig {
+            record_undo: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    env.place_all_markers(vec![0, 24]).unwrap();
+    let tile = env.legal_actions()[0];
+    let mut other = env.clone();
+    let mut deck = other.get_deck();
+    deck.reverse();
+    other.set_deck(deck).unwrap();
+
+    // the same tile on the same cell, but the other env draws different tiles
+    env.step_place_tile(tile).unwrap();
+    other.step_place_tile(tile).unwrap();
+    assert_eq!(env.board.tiles, other.board.tiles);
+    assert_ne!(env.player_hands, other.player_hands);
+    let snapshot = env.snapshot();
+    assert!(other.restore(&snapshot).is_err());
+
+    // replaying the undone move starts a new line as well
+    env.undo().unwrap();
+    env.step_place_tile(tile).unwrap();
+    assert!(env.restore(&snapshot).is_err());
+    let replayed = env.snapshot();
+    let mut clone = env.clone();
+    clone.step_place_tile(clone.legal_actions()[0]).unwrap();
+    clone.restore(&replayed).unwrap();
+    assert_eq!(clone, env);
+}
+
+#[test]
+fn test_without_record_undo() {
+    let mut env = TsuroEnv::new(Some(2), Some(0), EnvConfig::default()).unwrap();
+    env.step_place_marker(0).unwrap();
+    let marker_phase = (env.clone(), env.snapshot());
+    env.step_place_marker(24).unwrap();
+    env.restore(&marker_phase.1).unwrap();
+    assert_eq!(env, marker_phase.0);
+
+    env.step_place_marker(24).unwrap();
+    let tile_phase = env.snapshot();
+    env.step_place_tile(env.legal_actions()[0]).unwrap();
+    assert!(env.undo().is_err());
+    assert!(env.restore(&tile_phase).is_err());
+    assert!(env.undo_stack.is_empty());
+}
+
+/// run with cargo test --release -- --ignored --nocapture
+#[test]
+#[ignore]
+fn bench_snapshot_vs_clone() {
+    use std::hint::black_box;
+    use std::time::Instant;
+
+    let mut env = TsuroEnv::new(Some(4), Some(0), EnvConfig::default()).unwrap();
+    for position in [0, 12, 24, 36] {
+        env.step_place_marker(position).unwrap();
+    }
+    for _ in 0..10 {
+        let tile = env.legal_actions()[0];
+        env.step_place_tile(tile).unwrap();
+    }
+    let iterations = 100_000;
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(env.clone());
+    }
+    println!("clone:    {:?}", start.elapsed() / iterations);
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(env.snapshot());
+    }
+    println!("snapshot: {:?}", start.elapsed() / iterations);
+
+    // allocations are not counted, that would need a counting global allocator,
+    // the serialized sizes show how much less data a snapshot copies
+    // the env does not record undo, so the clone size is mostly the board graph
+    use bincode::config;
+    use bincode::serde::encode_to_vec;
+    let clone_bytes = encode_to_vec(&env, config::standard()).unwrap().len();
+    let snapshot_bytes = encode_to_vec(env.snapshot(), config::standard())
+        .unwrap()
+        .len();
+    println!("clone:    {clone_bytes} bytes serialized");
+    println!("snapshot: {snapshot_bytes} bytes serialized");
+    assert!(snapshot_bytes < clone_bytes);
+}
+
+#[test]
+fn test_set_deck() {
+    let mut env = TsuroEnv::new(Some(2), Some(0), EnvConfig::default()).unwrap();
+    let mut deck = env.get_deck();
+    deck.reverse();
+    let top = *deck.last().unwrap();
+    env.set_deck(deck.clone()).unwrap();
+    assert_eq!(env.get_deck(), deck);
+
+    let held = env.player_hands[0][0];
+    assert!(env.set_deck(vec![held]).is_err());
+    assert!(env.set_deck(vec![top, top.rotated(1)]).is_err());
+
+    env.step_place_marker(5).unwrap();
+    env.step_place_marker(29).unwrap();
+    let tile = env.legal_actions()[0];
+    env.step_place_tile(tile).unwrap();
+    // the player that just placed a tile draws first
+    assert!(env.board.markers[0].is_some());
+    assert!(env.player_hands[0].contains(&top));
+}
+
+#[test]
+fn test_set_deck_partial() {
+    let mut env = TsuroEnv::new(Some(2), Some(0), EnvConfig::default()).unwrap();
+    let deck = env.get_deck()[..5].to_vec();
+    env.set_deck(deck).unwrap();
+    assert!(!env.all_tiles_accounted());
+    env.place_all_markers(vec![0, 24]).unwrap();
+    while !env.terminated() {
+        if env.can_pass() {
+            env.step_pass().unwrap();
+        } else {
+            env.step_place_tile(env.legal_actions()[0]).unwrap();
+        }
+        assert!(env.no_tile_duplicated());
+    }
+}
+
+#[cfg(test)]
+fn env_in_tile_phase(num_players: usize) -> TsuroEnv {
+    let mut env =
+        TsuroEnv::new(Some(num_players), Some(0), EnvConfig::default()).unwrap();
+    for player in 0..num_players {
+        env.step_place_marker(player * 7).unwrap();
+    }
+    env
+}
+
+#[test]
+fn test_draw_tiles_skips_full_hands() {
+    let mut env = env_in_tile_phase(3);
+    // player 0 has a full hand, the others are missing tiles
+    env.deck.append(&mut env.player_hands[1].split_off(1));
+    env.deck.append(&mut env.player_hands[2].split_off(2));
+    let deck_len = env.deck.len();
+    env.draw_tiles();
+    assert!(env.player_hands.iter().all(|hand| hand.len() == 3));
+    assert_eq!(env.deck.len(), deck_len - 3);
+    assert_eq!(env.dragon_tile_owner, None);
+}
+
+#[test]
+fn test_draw_tiles_eliminated_player_does_not_draw() {
+    let mut env = env_in_tile_phase(3);
+    env.eliminate_player(0);
+    env.player_hands[1].pop();
+    env.draw_tiles();
+    assert!(env.player_hands[0].is_empty());
+    assert_eq!(env.player_hands[1].len(), 3);
+}
+
+#[test]
+fn test_draw_tiles_dragon_owner() {
+    let mut env = env_in_tile_phase(3);
+    let mut hand_1 = env.player_hands[1].split_off(1);
+    env.player_hands[2].pop();
+    let top = hand_1.pop().unwrap();
+    env.deck = vec![top];
+    env.draw_tiles();
+    // player 1 draws the only tile, player 2 is next and gets the dragon tile
+    assert_eq!(env.player_hands[1].len(), 2);
+    assert_eq!(env.player_hands[2].len(), 2);
+    assert_eq!(env.dragon_tile_owner, Some(2));
+
+    // once tiles are available again, the dragon tile owner draws first
+    env.deck = hand_1;
+    env.draw_tiles();
+    assert_eq!(env.player_hands[2].len(), 3);
+    assert_eq!(env.player_hands[1].len(), 2);
+    assert_eq!(env.dragon_tile_owner, Some(1));
+}
+
+#[test]
+fn test_last_elimination_terminates() {
+    let mut env = env_in_tile_phase(2);
+    let mut ret = None;
+    while env.num_players_left == 2 {
+        let tile = env.legal_actions()[0];
+        ret = Some(env.step_place_tile(tile).unwrap());
+    }
+    let ret = ret.unwrap();
+    assert!(ret.terminated);
+    assert!(ret.remaining_players.len() < 2);
+    assert!(env.legal_actions().is_empty());
+    // the turn can not pass to anyone else
+    for player in ret.remaining_players {
+        assert_eq!(env.player_after(player), None);
+    }
+}
+
+#[test]
+fn test_invalid_calls_return_errors() {
+    let mut env = TsuroEnv::new(Some(2), Some(0), EnvConfig::default()).unwrap();
+    let tile = env.player_hands[0][0];
+    assert!(env.step_place_tile(tile).is_err());
+    assert!(env.set_top_tile(tile).is_err());
+    env.step_place_marker(0).unwrap();
+    env.step_place_marker(1).unwrap();
+    assert!(env.step_place_marker(2).is_err());
+    let top = env.deck[0];
+    env.set_top_tile(top).unwrap();
+    assert_eq!(env.deck.last(), Some(&top));
+}
+
+#[test]
+fn test_spectator_view() {
+    let env = env_in_tile_phase(3);
+    let view = env.spectator_view();
+    assert_eq!(view.board, env.board);
+    assert_eq!(view.player_hands, env.player_hands);
+    assert_eq!(view.deck, env.get_deck());
+    assert_eq!(view.active_player, env.active_player);
+    assert_eq!(view.dragon_tile_owner, None);
+}
+
+#[test]
+fn test_history() {
+    let mut env = TsuroEnv::new(
+        Some(2),
+        Some(0),
+        EnvConfig {
+            record_history: true,
+            record_undo: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    env.step_place_marker(0).unwrap();
+    env.step_place_marker(0).unwrap(); // invalid, not recorded
+    env.step_place_marker(20).unwrap();
+    let tile = env.legal_actions()[0];
+    env.step_place_tile(tile).unwrap();
+    assert_eq!(
+        env.history(),
+        [
+            Move::PlaceMarker {
+                player: 0,
+                position: 0
+            },
+            Move::PlaceMarker {
+                player: 1,
+                position: 20
+            },
+            Move::PlaceTile { player: 0, tile },
+        ]
+    );
+
+    env.undo().unwrap();
+    assert_eq!(env.history().len(), 2);
+    env.reset();
+    assert!(env.history().is_empty());
+    assert!(env.record_history);
+
+    let mut env = TsuroEnv::new(Some(2), Some(0), EnvConfig::default()).unwrap();
+    env.step_place_marker(0).unwrap();
+    assert!(env.history().is_empty());
+}
+
+#[test]
+fn test_from_history() {
+    let mut env = TsuroEnv::new(
+        Some(3),
+        Some(4),
+        EnvConfig {
+            record_history: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for position in [0, 7, 14] {
+        env.step_place_marker(position).unwrap();
+    }
+    for _ in 0..5 {
+        let tile = env.legal_actions()[0];
+        env.step_place_tile(tile).unwrap();
+    }
+    let replayed = TsuroEnv::from_history(3, 4, env.history(), 3).unwrap();
+    assert_eq!(replayed, env);
+
+    let mut moves = env.history();
+    moves.swap(0, 1); // wrong player
+    assert!(TsuroEnv::from_history(3, 4, moves, 3).is_err());
+    let mut moves = env.history();
+    moves[1] = moves[0]; // occupied position
+    assert!(TsuroEnv::from_history(3, 4, moves, 3).is_err());
+    let moves = env.history();
+    // different deck, so the tiles are not in hand
+    assert!(TsuroEnv::from_history(3, 5, moves, 3).is_err());
+}
+
+#[test]
+fn test_place_symmetric_tile() {
+    let mut env = env_in_tile_phase(2);
+    // 16-25-38-47 looks the same in every rotation
+    let symmetric = ALL_TILES[3];
+    let other = ALL_TILES[20];
+    env.player_hands[0] = vec![other, symmetric, other.rotated(1)];
+    let idx = env.active_player_hand_find_tile(symmetric.rotated(3));
+    assert_eq!(idx, Some(1));
+    assert_eq!(env.active_player_hand_find_tile(other.rotated(2)), Some(0));
+
+    // the other tiles all go to player 1 so placing does not draw a new tile
+    let rest = ALL_TILES.iter().copied();
+    let rest = rest
+        .filter(|tile| ![other, symmetric].contains(tile))
+        .collect();
+    env.player_hands = vec![vec![other, symmetric], rest];
+    env.deck.clear();
+    env.step_place_tile(symmetric.rotated(2)).unwrap();
+    assert_eq!(env.player_hands[0], [other]);
+}
+
+#[test]
+#[cfg(feature = "board-5x5")]
+fn test_small_board() {
+    assert_eq!(NUM_NODES, 120);
+    assert_eq!(NUM_EDGE_POSITIONS, 40);
+    let mut env = env_in_tile_phase(4);
+    assert_eq!(env.board.graph.vertices.iter().flatten().count(), 120);
+    let edges = (0..NUM_EDGE_POSITIONS).map(MarkerPosition::from_index);
+    assert!(edges.clone().all(|position| position.is_edge()));
+    assert_eq!(
+        edges.map(|position| position.node_id()).unique().count(),
+        40
+    );
+    play_until_terminated(&mut env);
+    let num_tiles = env.board.tiles.as_flattened().iter().flatten().count();
+    assert!(num_tiles <= BOARD_SIZE * BOARD_SIZE);
+}
+
+#[test]
+fn test_env_return_players() {
+    let mut env = TsuroEnv::new(Some(2), Some(0), EnvConfig::default()).unwrap();
+    let ret = env.step_place_marker(0).unwrap();
+    assert_eq!((ret.acted_player, ret.active_player), (0, 1));
+    assert!(ret.legal_actions.is_empty());
+
+    let ret = env.step_place_marker(20).unwrap();
+    assert_eq!((ret.acted_player, ret.active_player), (1, 0));
+    assert_eq!(ret.phase, Phase::Tiles);
+    assert_eq!(ret.legal_actions, env.legal_actions());
+    assert!(!ret.legal_actions.is_empty());
+
+    let tile = ret.legal_actions[0];
+    let ret = env.step_place_tile(tile).unwrap();
+    assert_eq!((ret.acted_player, ret.active_player), (0, 1));
+    assert_eq!(ret.view.active_player, 1);
+    assert_eq!(ret.legal_actions, env.legal_actions());
+
+    let not_held = ALL_TILES
+        .iter()
+        .find(|tile| env.active_player_hand_find_tile(**tile).is_none())
+        .unwrap();
+    let ret = env.step_place_tile(*not_held).unwrap();
+    assert!(!ret.move_is_valid);
+    assert_eq!((ret.acted_player, ret.active_player), (1, 1));
+}
+
+#[test]
+fn test_remaining_tile_counts() {
+    let mut env = env_in_tile_phase(2);
+    assert_eq!(env.deck_size(), ALL_TILES.len() - 6);
+    let counts = env.remaining_tile_counts();
+    assert_eq!(counts.iter().sum::<usize>(), ALL_TILES.len() - 3);
+    for tile in &env.player_hands[env.active_player] {
+        assert_eq!(counts[tile.index().unwrap()], 0);
+    }
+    for tile in env.player_hands[1].iter().chain(&env.deck) {
+        assert_eq!(counts[tile.index().unwrap()], 1);
+    }
+
+    let tile = env.legal_actions()[0];
+    env.step_place_tile(tile).unwrap();
+    let counts = env.remaining_tile_counts();
+    assert_eq!(counts[tile.index().unwrap()], 0);
+    assert_eq!(
+        counts.iter().sum::<usize>(),
+        env.deck_size() + env.player_hands[0].len()
+    );
+}
+
+#[test]
+fn test_step_pass() {
+    let mut env = TsuroEnv::new(
+        Some(2),
+        Some(0),
+        EnvConfig {
+            record_history: true,
+            record_undo: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(env.step_pass().is_err());
+    env.step_place_marker(0).unwrap();
+    env.step_place_marker(20).unwrap();
+    assert!(env.step_pass().is_err());
+
+    let tile = env.legal_actions()[0];
+    env.step_place_tile(tile).unwrap();
+    // player 1 ran out of tiles
+    let mut hand = std::mem::take(&mut env.player_hands[1]);
+    env.deck.append(&mut hand);
+    assert!(env.legal_actions().is_empty());
+    let ret = env.step_pass().unwrap();
+    assert!(ret.move_is_valid);
+    assert_eq!((ret.acted_player, ret.active_player), (1, 0));
+    assert_eq!(env.history().last(), Some(&Move::Pass { player: 1 }));
+
+    // only the pass is undone, the tile stays on the board
+    env.undo().unwrap();
+    assert_eq!(env.active_player, 1);
+    assert_eq!(env.history().len(), 3);
+    assert!(matches!(env.history().last(), Some(Move::PlaceTile { .. })));
+    let num_tiles =
+        |env: &TsuroEnv| env.board.tiles.as_flattened().iter().flatten().count();
+    assert_eq!(num_tiles(&env), 1);
+    assert!(env.player_hands[1].is_empty());
+
+    env.undo().unwrap();
+    assert_eq!(env.active_player, 0);
+    assert_eq!(env.history().len(), 2);
+    assert_eq!(num_tiles(&env), 0);
+}
+
+#[test]
+fn test_all_tiles_accounted() {
+    let mut env = env_in_tile_phase(3);
+    assert!(env.all_tiles_accounted());
+    while !env.terminated() {
+        let tile = env.legal_actions()[0];
+        env.step_place_tile(tile).unwrap();
+        assert!(env.all_tiles_accounted());
+    }
+
+    let mut env = env_in_tile_phase(2);
+    let tile = env.deck.pop().unwrap();
+    assert!(!env.all_tiles_accounted());
+    env.player_hands[1].push(tile.rotated(1));
+    assert!(env.all_tiles_accounted());
+    env.deck.push(tile);
+    assert!(!env.all_tiles_accounted());
+}
+
+#[test]
+fn test_remaining_players() {
+    let mut env = TsuroEnv::new(Some(3), Some(0), EnvConfig::default()).unwrap();
+    env.step_place_marker(0).unwrap();
+    assert_eq!(env.remaining_players(), vec![0, 1, 2]);
+    assert_eq!(env.num_players_left(), 3);
+    env.step_place_marker(7).unwrap();
+    env.step_place_marker(14).unwrap();
+    env.eliminate_player(1);
+    assert_eq!(env.remaining_players(), vec![0, 2]);
+    assert_eq!(env.num_players_left(), 2);
+    assert_eq!(
+        env.get_return(true).remaining_players,
+        env.remaining_players()
+    );
+}
+
+#[test]
+fn test_winners() {
+    let mut env = env_in_tile_phase(3);
+    assert!(env.winners().is_err());
+    env.eliminate_player(1);
+    env.eliminate_player(2);
+    assert_eq!(env.winners().unwrap(), vec![0]);
+
+    let mut env = env_in_tile_phase(2);
+    env.eliminate_player(0);
+    env.eliminate_player(1);
+    assert!(env.winners().unwrap().is_empty());
+
+    let mut env = env_in_tile_phase(2);
+    env.deck.clear();
+    env.player_hands = vec![vec![], vec![]];
+    assert_eq!(env.winners().unwrap(), vec![0, 1]);
+}
+
+#[test]
+fn test_termination_reason() {
+    let mut env = env_in_tile_phase(3);
+    assert_eq!(env.termination_reason(), None);
+    env.eliminate_player(1);
+    assert_eq!(env.get_return(true).termination_reason, None);
+    env.eliminate_player(2);
+    assert_eq!(
+        env.get_return(true).termination_reason,
+        Some(TerminationReason::Elimination)
+    );
+
+    let mut env = env_in_tile_phase(2);
+    env.deck.clear();
+    env.player_hands = vec![vec![], vec![]];
+    assert_eq!(
+        env.termination_reason(),
+        Some(TerminationReason::TilesExhausted)
+    );
+}
+
+#[test]
+fn test_place_marker_out_of_range() {
+    let mut env = TsuroEnv::new(
+        Some(2),
+        Some(0),
+        EnvConfig {
+            record_history: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let ret = env.step_place_marker(NUM_EDGE_POSITIONS).unwrap();
+    assert!(!ret.move_is_valid);
+    assert_eq!(ret.active_player, 0);
+    assert!(env.history().is_empty());
+    assert!(!env.step_place_marker(usize::MAX).unwrap().move_is_valid);
+    assert!(
+        env.step_place_marker(NUM_EDGE_POSITIONS - 1)
+            .unwrap()
+            .move_is_valid
+    );
+}
+
+#[test]
+fn test_rollout_random() {
+    for seed in 0..20 {
+        let mut env = TsuroEnv::new(
+            Some(4),
+            Some(seed),
+            EnvConfig {
+                record_history: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(env.rollout_random(seed).len() <= 4);
+        for position in [0, 12, 24, 36] {
+            env.step_place_marker(position).unwrap();
+        }
+        for _ in 0..6 {
+            let Some(tile) = env.legal_actions().first().copied() else {
+                break;
+            };
+            env.step_place_tile(tile).unwrap();
+        }
+        let before = env.clone();
+        let alive = env.remaining_players();
+        let winners = env.rollout_random(seed);
+        assert!(winners.iter().all(|player| alive.contains(player)));
+        assert_eq!(winners, env.rollout_random(seed));
+        assert_eq!(env, before);
+    }
+}
+
+/// run with cargo test --release -- --ignored --nocapture
+#[test]
+#[ignore]
+fn bench_rollout_random() {
+    use std::hint::black_box;
+    use std::time::Instant;
+
+    let env = TsuroEnv::new(Some(4), Some(0), EnvConfig::default()).unwrap();
+    let iterations = 10_000;
+    let start = Instant::now();
+    for seed in 0..iterations {
+        black_box(env.rollout_random(seed));
+    }
+    println!("rollout: {:?}", start.elapsed() / iterations as u32);
+}
+
+#[test]
+fn test_active_player_collides() {
+    let mut env = TsuroEnv::new(Some(4), Some(0), EnvConfig::default()).unwrap();
+    // 0 and 1 are the two positions on the side of the corner tile,
+    // the u turn joins them
+    for position in [0, 1, 20, 30] {
+        env.step_place_marker(position).unwrap();
+    }
+    let u_turn = Tile::from_code("12-34-56-78").unwrap();
+    let rest = ALL_TILES.iter().copied().filter(|tile| *tile != u_turn);
+    let rest = rest.collect_vec();
+    env.player_hands = vec![
+        vec![u_turn],
+        rest[0..3].to_vec(),
+        rest[3..6].to_vec(),
+        rest[6..9].to_vec(),
+    ];
+    env.deck = rest[9..].to_vec();
+    let (x, y) = env.board.next_tile_of_player(0);
+
+    let ret = env.step_place_tile(u_turn).unwrap();
+    assert!(ret.move_is_valid);
+    assert_eq!(ret.remaining_players, [2, 3]);
+    assert_eq!(env.board.tiles[x][y], Some(u_turn));
+    assert!(env.all_tiles_accounted());
+}
+
+#[test]
+fn test_stacked_observation() {
+    let mut env = TsuroEnv::new(
+        Some(2),
+        Some(0),
+        EnvConfig {
+            frame_history: 3,
+            record_undo: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(env.stacked_observation(4).is_err());
+    let empty = Board::new().tile_one_hot();
+    let stacked = env.stacked_observation(3).unwrap().0;
+    let size = BOARD_SIZE as i64;
+    assert_eq!(stacked.size(), [3, size, size, 36]);
+    // two frames of padding and the empty board
+    let num_tiles = (BOARD_SIZE * BOARD_SIZE) as i64;
+    assert_eq!(stacked.sum(tch::Kind::Int64).int64_value(&[]), num_tiles);
+    assert_eq!(stacked.get(0).sum(tch::Kind::Int64).int64_value(&[]), 0);
+
+    env.step_place_marker(0).unwrap();
+    env.step_place_marker(20).unwrap();
+    let mut boards = vec![empty];
+    for _ in 0..3 {
+        let tile = env.legal_actions()[0];
+        env.step_place_tile(tile).unwrap();
+        boards.push(env.board.tile_one_hot());
+    }
+    let stacked = env.stacked_observation(3).unwrap().0;
+    let expected = boards[1..].concat();
+    assert_eq!(
+        Vec::<i64>::try_from(stacked.reshape(-1)).unwrap(),
+        expected.iter().map(|x| *x as i64).collect_vec()
+    );
+    assert_eq!(env.stacked_observation(0).unwrap().0.numel(), 0);
+
+    env.undo().unwrap();
+    let stacked = env.stacked_observation(3).unwrap().0;
+    assert_eq!(stacked.get(0).sum(tch::Kind::Int64).int64_value(&[]), 0);
+    let last = Vec::<i64>::try_from(stacked.get(2).reshape(-1)).unwrap();
+    assert_eq!(last, boards[2].iter().map(|x| *x as i64).collect_vec());
+
+    env.reset();
+    assert_eq!(env.frames.len(), 1);
+    assert_eq!(
+        TsuroEnv::new(Some(2), Some(0), EnvConfig::default())
+            .unwrap()
+            .frames
+            .len(),
+        0
+    );
+}
+
+#[test]
+fn test_place_all_markers() {
+    let mut env = TsuroEnv::new(
+        Some(3),
+        Some(0),
+        EnvConfig {
+            record_history: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(env.place_all_markers(vec![0, 10]).is_err());
+    assert!(env
+        .place_all_markers(vec![0, 10, NUM_EDGE_POSITIONS])
+        .is_err());
+    assert!(env.place_all_markers(vec![0, 10, 0]).is_err());
+    assert!(env.history().is_empty());
+
+    let ret = env.place_all_markers(vec![0, 10, 20]).unwrap();
+    assert!(ret.move_is_valid);
+    assert_eq!(ret.phase, Phase::Tiles);
+    assert_eq!(ret.active_player, 0);
+    assert_eq!(env.history().len(), 3);
+    let mut expected = TsuroEnv::new(
+        Some(3),
+        Some(0),
+        EnvConfig {
+            record_history: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for position in [0, 10, 20] {
+        expected.step_place_marker(position).unwrap();
+    }
+    assert_eq!(env, expected);
+    assert!(env.place_all_markers(vec![1, 11, 21]).is_err());
+}
+
+#[test]
+fn test_place_random_markers() {
+    for num_players in PLAYER_RANGE {
+        let mut env =
+            TsuroEnv::new(Some(num_players), Some(0), EnvConfig::default()).unwrap();
+        let ret = env.place_random_markers(num_players as u64);
+        assert_eq!(ret.phase, Phase::Tiles);
+        let positions = env.board.markers.iter().map(|m| m.unwrap().position);
+        assert_eq!(positions.clone().count(), num_players);
+        assert!(positions.clone().all_unique());
+
+        let mut same =
+            TsuroEnv::new(Some(num_players), Some(0), EnvConfig::default()).unwrap();
+        same.place_random_markers(num_players as u64);
+        assert_eq!(env, same);
+        // nothing left to place
+        assert_eq!(env.place_random_markers(0).phase, Phase::Tiles);
+        assert_eq!(env, same);
+    }
+}
+
+#[test]
+fn test_deck_tensor() {
+    let mut env = TsuroEnv::new(Some(3), Some(0), EnvConfig::default()).unwrap();
+    env.place_random_markers(0);
+    for _ in 0..4 {
+        let tensor = env.deck_tensor().0;
+        assert_eq!(tensor.size(), [ALL_TILES.len() as i64]);
+        let sum = tensor.sum(tch::Kind::Int64).int64_value(&[]);
+        assert_eq!(sum, env.deck_size() as i64);
+        for tile in &env.deck {
+            assert_eq!(tensor.int64_value(&[tile.index().unwrap() as i64]), 1);
+        }
+        let tile = env.legal_actions()[0];
+        env.step_place_tile(tile).unwrap();
+    }
+    env.deck.clear();
+    let empty = env.deck_tensor().0;
+    assert_eq!(empty.sum(tch::Kind::Int64).int64_value(&[]), 0);
+}
+
+#[test]
+fn test_marker_phase_observation() {
+    let mut env = TsuroEnv::new(Some(3), Some(0), EnvConfig::default()).unwrap();
+    let observation = env.marker_phase_observation().0;
+    assert_eq!(observation.size(), [NUM_EDGE_POSITIONS as i64 + 1]);
+    assert_eq!(observation.int64_value(&[NUM_EDGE_POSITIONS as i64]), 3);
+    let total = observation.sum(tch::Kind::Int64).int64_value(&[]);
+    assert_eq!(total, 3); // only the markers to place
+
+    env.step_place_marker(7).unwrap();
+    env.step_place_marker(30).unwrap();
+    let observation = env.marker_phase_observation().0;
+    for idx in 0..NUM_EDGE_POSITIONS {
+        let expected = [7, 30].contains(&idx) as i64;
+        assert_eq!(observation.int64_value(&[idx as i64]), expected);
+    }
+    assert_eq!(observation.int64_value(&[NUM_EDGE_POSITIONS as i64]), 1);
+}
+
+#[test]
+fn test_step_place_tile_action() {
+    let mut env = TsuroEnv::new(Some(2), Some(0), EnvConfig::default()).unwrap();
+    env.place_all_markers(vec![0, 24]).unwrap();
+    assert!(env.step_place_tile_action(0, 4).is_err());
+    assert!(env.step_place_tile_action(3, 0).is_err());
+
+    let mut expected = env.clone();
+    let player = env.active_player;
+    let (hand_index, rotation) = (0..3)
+        .cartesian_product(0..4)
+        .find(|(idx, rot)| {
+            let tile = env.player_hands[player][*idx].rotated(*rot);
+            env.legal_actions().contains(&tile)
+        })
+        .unwrap();
+    let tile = env.player_hands[player][hand_index].rotated(rotation);
+    let ret = env.step_place_tile_action(hand_index, rotation).unwrap();
+    assert!(ret.move_is_valid);
+    assert_eq!(ret, expected.step_place_tile(tile).unwrap());
+    assert_eq!(env, expected);
+}
+
+#[test]
+fn test_invalid_reason() {
+    let mut env = TsuroEnv::new(Some(2), Some(0), EnvConfig::default()).unwrap();
+    let ret = env.step_place_marker(NUM_EDGE_POSITIONS).unwrap();
+    assert_eq!(
+        ret.invalid_reason,
+        Some(InvalidMoveReason::PositionOutOfRange)
+    );
+    let ret = env.step_place_marker(0).unwrap();
+    assert_eq!(ret.invalid_reason, None);
+    let ret = env.step_place_marker(0).unwrap();
+    assert_eq!(ret.invalid_reason, Some(InvalidMoveReason::PositionTaken));
+    env.step_place_marker(24).unwrap();
+
+    let not_held = ALL_TILES
+        .iter()
+        .find(|tile| env.active_player_hand_find_tile(**tile).is_none())
+        .unwrap();
+    let ret = env.step_place_tile(*not_held).unwrap();
+    assert!(!ret.move_is_valid);
+    assert_eq!(ret.invalid_reason, Some(InvalidMoveReason::NotInHand));
+
+    let mut found_suicide = false;
+    while !env.legal_actions().is_empty() {
+        let legal_actions = env.legal_actions();
+        let player = env.active_player;
+        let rejected = env
+            .all_rotated_tiles_of(player)
+            .find(|tile| !legal_actions.contains(tile));
+        if let Some(tile) = rejected {
+            assert!(env.board.move_is_suicide(tile, player));
+            let ret = env.step_place_tile(tile).unwrap();
+            assert_eq!(
+                ret.invalid_reason,
+                Some(InvalidMoveReason::SuicideNotForced)
+            );
+            found_suicide = true;
+        }
+        let ret = env.step_place_tile(legal_actions[0]).unwrap();
+        assert_eq!(ret.invalid_reason, None);
+    }
+    assert!(found_suicide);
+}
@@ -1,6 +1,8 @@
-use std::cell::LazyCell;
+use std::collections::VecDeque;
+use std::sync::LazyLock;
 
 use pyo3::prelude::*;
+use rand_chacha::ChaCha12Rng;
 use serde::{Deserialize, Serialize};
 use serde_big_array::BigArray;
 
@@ -8,19 +10,33 @@ mod board;
 mod env;
 mod pymethods;
 mod tile;
+mod vec_env;
 mod view;
 
 #[cfg(test)]
 pub(crate) use tile::find_tile_with_connection;
 pub(crate) type Coord = (usize, usize);
+
+/// number of tiles along each side of the board
+#[cfg(not(feature = "board-5x5"))]
+pub const BOARD_SIZE: usize = 6;
+/// number of tiles along each side of the board
+#[cfg(feature = "board-5x5")]
+pub const BOARD_SIZE: usize = 5;
+/// number of points along each side of the lattice marker positions are on
+pub const LATTICE_SIZE: usize = BOARD_SIZE * 3 + 1;
+/// number of marker positions, these are the nodes of the board graph
+pub const NUM_NODES: usize = 4 * BOARD_SIZE * (BOARD_SIZE + 1);
+/// number of marker positions on the edge of the board
+pub const NUM_EDGE_POSITIONS: usize = 8 * BOARD_SIZE;
 pub use tile::ALL_TILES;
 
 #[pyclass(module = "py_tsuro")]
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, Hash)]
 pub struct Board {
     pub markers: Vec<Option<Marker>>,
     #[pyo3(get)]
-    pub tiles: [[Option<Tile>; 6]; 6],
+    pub tiles: [[Option<Tile>; BOARD_SIZE]; BOARD_SIZE],
     #[pyo3(get)]
     pub graph: BoardGraph,
 }
@@ -44,7 +60,7 @@ pub struct Tile {
 }
 
 #[pyclass(module = "py_tsuro")]
-#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq, Hash)]
 pub struct View {
     #[pyo3(get)]
     pub board: Board,
@@ -54,21 +70,42 @@ pub struct View {
     pub active_player: usize,
 }
 
+/// everything an observer can know about the game, including hidden information
+/// only returned by TsuroEnv.spectator_view, agents should be given a View
 #[pyclass(module = "py_tsuro")]
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+pub struct FullView {
+    #[pyo3(get)]
+    pub board: Board,
+    /// hands of every player, indexed by player
+    #[pyo3(get)]
+    pub player_hands: Vec<Vec<Tile>>,
+    /// in the same order as TsuroEnv.get_deck, the last tile is drawn first
+    #[pyo3(get)]
+    pub deck: Vec<Tile>,
+    #[pyo3(get)]
+    pub active_player: usize,
+    #[pyo3(get)]
+    pub dragon_tile_owner: Option<usize>,
+}
+
+/// PartialEq and Hash are implemented in graph.rs,
+/// they ignore differences caused by the order tiles were placed in
+#[pyclass(module = "py_tsuro")]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BoardGraph {
     #[pyo3(get)]
     #[serde(with = "BigArray")]
-    pub vertices: [Option<MarkerPosition>; 168],
+    pub vertices: [Option<MarkerPosition>; NUM_NODES],
     /// adjacency_list[from_id] == [(to_id, built)]
     /// to_id: id of the other node
     /// built: wether the path is built
     #[pyo3(get)]
     #[serde(with = "BigArray")]
-    pub adjacency_list: [Vec<(usize, bool)>; 168],
+    pub adjacency_list: [Vec<(usize, bool)>; NUM_NODES],
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[pyclass(module = "py_tsuro", subclass)]
 pub struct TsuroEnv {
     board: Board,
@@ -78,8 +115,112 @@ pub struct TsuroEnv {
     active_player: usize,
     player_hands: Vec<Vec<Tile>>,
     deck: Vec<Tile>,
+    /// player who draws first once the deck is refilled
+    #[pyo3(get)]
     dragon_tile_owner: Option<usize>,
     num_players_left: usize,
+    /// number of tiles each player is dealt and refills to
+    #[pyo3(get)]
+    hand_size: usize,
+    /// used for every shuffle of the deck, serialized so pickling
+    /// preserves the random sequence
+    rng: ChaCha12Rng,
+    /// wether tile placements and passes are recorded so they can be undone
+    #[pyo3(get)]
+    record_undo: bool,
+    /// one record for each tile placement and pass, used by undo
+    /// always empty unless record_undo is set
+    undo_stack: Vec<env::UndoRecord>,
+    /// wether valid moves are appended to history
+    #[pyo3(get)]
+    record_history: bool,
+    history: Vec<Move>,
+    /// number of frames kept for stacked_observation, 0 keeps none
+    #[pyo3(get)]
+    frame_history: usize,
+    /// flattened Board::tile_tensor of the last frame_history boards, oldest first
+    frames: VecDeque<Vec<i8>>,
+    #[pyo3(get)]
+    reward_config: RewardConfig,
+}
+
+/// the options of TsuroEnv that come after the seed, the keyword-only
+/// arguments of the python constructor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvConfig {
+    /// how many tiles every player holds, at least 1
+    pub hand_size: usize,
+    /// whether the placements are kept for TsuroEnv.history
+    pub record_history: bool,
+    /// whether moves can be undone, needed by restore past a tile placement
+    pub record_undo: bool,
+    /// the most frames stacked_observation can stack
+    pub frame_history: usize,
+    pub reward_config: RewardConfig,
+}
+
+/// weights of the terms that make up EnvReturn.rewards after a tile placement,
+/// the default only rewards winning and penalizes being eliminated
+/// marker placements and passes are always rewarded with 0
+#[pyclass(module = "py_tsuro")]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RewardConfig {
+    /// given to every player still on the board after the placement
+    #[pyo3(get)]
+    pub survival_bonus: f32,
+    /// multiplied by how many tiles the path of a surviving player grew by,
+    /// see Board.path_length
+    #[pyo3(get)]
+    pub path_length_weight: f32,
+    /// subtracted from the reward of a player eliminated by the placement,
+    /// they get no other term
+    #[pyo3(get)]
+    pub elimination_penalty: f32,
+    /// given to every player still on the board once the game has ended
+    #[pyo3(get)]
+    pub win_bonus: f32,
+}
+
+/// a valid move made in a TsuroEnv, see TsuroEnv.history
+#[pyclass(module = "py_tsuro")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Move {
+    /// position is the edge index the marker was placed on
+    PlaceMarker { player: usize, position: usize },
+    /// tile is the rotated tile that was placed
+    PlaceTile { player: usize, tile: Tile },
+    /// the player had no tiles left, see TsuroEnv.step_pass
+    Pass { player: usize },
+}
+
+/// mutable state of a TsuroEnv except the board graph,
+/// which restore rebuilds from the undo records instead of copying it
+#[pyclass(module = "py_tsuro")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Snapshot {
+    tiles: [[Option<Tile>; BOARD_SIZE]; BOARD_SIZE],
+    markers: Vec<Option<Marker>>,
+    num_markers_placed: usize,
+    phase: Phase,
+    active_player: usize,
+    player_hands: Vec<Vec<Tile>>,
+    deck: Vec<Tile>,
+    dragon_tile_owner: Option<usize>,
+    num_players_left: usize,
+    rng: ChaCha12Rng,
+    undo_depth: usize,
+    /// id of the last undo record at undo_depth, restore rejects the snapshot
+    /// if that record has been undone since
+    undo_top: Option<u64>,
+    history_len: usize,
+}
+
+/// a batch of independent environments stepped together
+/// they are stepped in parallel if the rayon feature is enabled
+#[pyclass(module = "py_tsuro")]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct VecTsuroEnv {
+    envs: Vec<TsuroEnv>,
 }
 
 #[pyclass(module = "py_tsuro")]
@@ -89,13 +230,18 @@ pub struct TsuroEnv {
 pub struct Marker {
     #[pyo3(get)]
     pub position: MarkerPosition,
+    /// tile the marker arrived from, None while it is on its starting edge
+    #[pyo3(get)]
     pub(crate) previous_tile: Option<Coord>,
+    /// wether the marker has left its starting position
+    #[pyo3(get)]
     pub(crate) has_moved: bool,
 }
 
 /// marker positions can be understood as coordinates on the 19x19 lattice
 /// created by splitting each tile in 3x3 subtiles,
 /// or as a 6x6 coordinate plus the entry point index
+/// (LATTICE_SIZE and BOARD_SIZE with the board-5x5 feature)
 /// entry point indices start from 0 at the left point on the south side
 /// this struct provides methods to view and manage it as either
 #[pyclass(module = "py_tsuro", name = "MarkerPosition")]
@@ -107,42 +253,115 @@ pub struct MarkerPosition {
 }
 
 #[pyclass(module = "py_tsuro")]
-#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[derive(
+    PartialEq, Eq, Hash, Debug, Clone, Copy, Serialize, Deserialize, Default,
+)]
 pub enum Phase {
     #[default]
     Markers = 0,
     Tiles = 1,
 }
 
+/// why a game ended, see TsuroEnv.termination_reason
+#[pyclass(module = "py_tsuro")]
+#[derive(
+    PartialEq, Eq, Hash, Debug, Clone, Copy, Serialize, Deserialize, Default,
+)]
+pub enum TerminationReason {
+    /// fewer than two players are left, the survivor (if any) wins
+    #[default]
+    Elimination = 0,
+    /// the deck and all hands are empty, every player left survives
+    TilesExhausted = 1,
+}
+
+/// why a step rejected a move, see EnvReturn.invalid_reason
+/// steps after the game has terminated raise instead
+#[pyclass(module = "py_tsuro")]
+#[derive(
+    PartialEq, Eq, Hash, Debug, Clone, Copy, Serialize, Deserialize, Default,
+)]
+pub enum InvalidMoveReason {
+    /// the tile is not a rotation of a tile in the active player's hand
+    #[default]
+    NotInHand = 0,
+    /// the tile eliminates the active player while another move would not
+    SuicideNotForced = 1,
+    /// the marker position is not one of the NUM_EDGE_POSITIONS edge positions
+    PositionOutOfRange = 2,
+    /// another marker is already on the position
+    PositionTaken = 3,
+}
+
+/// result of a step, describing the state after the move
+/// acted_player is the player who made the move, rewards and move_is_valid are about it,
+/// active_player is the player who moves next, view and legal_actions are for them
+/// after an invalid move or a reset the two are the same player
 #[pyclass(module = "py_tsuro")]
 #[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
 pub struct EnvReturn {
+    /// view of active_player
     #[pyo3(get)]
     pub view: View,
     #[pyo3(get)]
     pub terminated: bool,
+    /// None while the game is running
+    #[pyo3(get)]
+    pub termination_reason: Option<TerminationReason>,
     #[pyo3(get)]
     pub move_is_valid: bool,
+    /// None unless move_is_valid is false
+    #[pyo3(get)]
+    pub invalid_reason: Option<InvalidMoveReason>,
+    #[pyo3(get)]
+    pub acted_player: usize,
     #[pyo3(get)]
     pub active_player: usize,
+    /// TsuroEnv.legal_actions for active_player, empty outside of the tile phase
+    #[pyo3(get)]
+    pub legal_actions: Vec<Tile>,
     #[pyo3(get)]
     pub remaining_players: Vec<usize>,
     #[pyo3(get)]
     pub phase: Phase,
+    /// rewards[player] is -1 if the player was eliminated by this move,
+    /// +1 if the game ended and the player survived, 0 otherwise
+    /// with the default TsuroEnv.reward_config
+    #[pyo3(get)]
+    pub rewards: Vec<f32>,
+    /// player who draws first once the deck is refilled
+    #[pyo3(get)]
+    pub dragon_tile_owner: Option<usize>,
 }
 
-pub const ALL_NODES: LazyCell<Vec<MarkerPosition>> = LazyCell::new(|| {
-    let mut out = Vec::with_capacity(168);
-    for x in 0..19 {
-        for y in 0..19 {
-            if (x % 3 == 0) ^ (y % 3 == 0) {
-                out.push(MarkerPosition::from_lattice_coordinates((x, y)));
-            }
-        }
-    }
-    out
+/// every marker position, ALL_NODES[id].node_id() == id
+pub static ALL_NODES: LazyLock<Vec<MarkerPosition>> = LazyLock::new(|| {
+    (0..NUM_NODES)
+        .map(|id| MarkerPosition::from_node_id(id).unwrap())
+        .collect()
 });
 
+/// every marker position, indexed by node id
+#[pyfunction]
+fn all_nodes() -> Vec<MarkerPosition> {
+    ALL_NODES.clone()
+}
+
+/// BoardGraph.adjacency_matrix_tensor of every graph in one
+/// [len(graphs), NUM_NODES, NUM_NODES] tensor
+#[pyfunction]
+fn stack_adjacency_tensors(graphs: Vec<BoardGraph>) -> pyo3_tch::PyTensor {
+    let matrix_size = NUM_NODES * NUM_NODES;
+    let mut matrices = vec![0i8; graphs.len() * matrix_size];
+    let chunks = matrices.chunks_exact_mut(matrix_size);
+    for (graph, matrix) in graphs.iter().zip(chunks) {
+        graph.fill_adjacency_matrix(matrix);
+    }
+    let size = NUM_NODES as i64;
+    let shape = [graphs.len() as i64, size, size];
+    pyo3_tch::PyTensor(tch::Tensor::from_slice(&matrices).view(shape))
+}
+
 #[pymodule]
 fn py_tsuro<'py>(py: Python<'py>, m: &Bound<'_, PyModule>) -> PyResult<()> {
     py.import("torch")?;
@@ -151,11 +370,21 @@ fn py_tsuro<'py>(py: Python<'py>, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<View>()?;
     m.add_class::<TsuroEnv>()?;
     m.add_class::<MarkerPosition>()?;
+    m.add_class::<Marker>()?;
     m.add_class::<Phase>()?;
     m.add_class::<BoardGraph>()?;
     m.add_class::<Board>()?;
+    m.add_class::<Snapshot>()?;
+    m.add_class::<VecTsuroEnv>()?;
+    m.add_class::<FullView>()?;
+    m.add_class::<Move>()?;
+    m.add_class::<TerminationReason>()?;
+    m.add_class::<InvalidMoveReason>()?;
+    m.add_class::<RewardConfig>()?;
 
-    #[allow(clippy::borrow_interior_mutable_const)]
     m.add("ALL_TILES", *ALL_TILES)?;
-    m.add("ALL_NODES", ALL_NODES.clone())
+    m.add("ALL_NODES", ALL_NODES.clone())?;
+    m.add_function(wrap_pyfunction!(all_nodes, m)?)?;
+    m.add_function(wrap_pyfunction!(stack_adjacency_tensors, m)?)?;
+    m.add("BOARD_SIZE", BOARD_SIZE)
 }
@@ -2,21 +2,92 @@ use itertools::Itertools;
 use pyo3::{prelude::*, types::PyBytes};
 use std::default::Default;
 use std::fmt::Debug;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use bincode::config;
 use bincode::serde::{encode_to_vec, decode_from_slice};
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
 
 use crate::*;
 
 impl Default for BoardGraph {
     fn default() -> Self {
         Self {
-            vertices: [None; 168],
-            adjacency_list: [const { vec![] }; 168],
+            vertices: [None; NUM_NODES],
+            adjacency_list: [const { vec![] }; NUM_NODES],
         }
     }
 }
 
-macro_rules! impl_python_other_methods {
+impl Default for TsuroEnv {
+    fn default() -> Self {
+        Self {
+            board: Default::default(),
+            num_markers_placed: 0,
+            phase: Default::default(),
+            num_players: 0,
+            active_player: 0,
+            player_hands: vec![],
+            deck: vec![],
+            dragon_tile_owner: None,
+            num_players_left: 0,
+            hand_size: 3,
+            rng: ChaCha12Rng::seed_from_u64(0),
+            record_undo: false,
+            undo_stack: vec![],
+            record_history: false,
+            history: vec![],
+            frame_history: 0,
+            frames: VecDeque::new(),
+            reward_config: Default::default(),
+        }
+    }
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        Self {
+            survival_bonus: 0.0,
+            path_length_weight: 0.0,
+            elimination_penalty: 1.0,
+            win_bonus: 1.0,
+        }
+    }
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            hand_size: 3,
+            record_history: false,
+            record_undo: false,
+            frame_history: 0,
+            reward_config: RewardConfig::default(),
+        }
+    }
+}
+
+impl Default for Snapshot {
+    fn default() -> Self {
+        Self {
+            tiles: [[None; BOARD_SIZE]; BOARD_SIZE],
+            markers: vec![],
+            num_markers_placed: 0,
+            phase: Default::default(),
+            active_player: 0,
+            player_hands: vec![],
+            deck: vec![],
+            dragon_tile_owner: None,
+            num_players_left: 0,
+            rng: ChaCha12Rng::seed_from_u64(0),
+            undo_depth: 0,
+            undo_top: None,
+            history_len: 0,
+        }
+    }
+}
+
+macro_rules! impl_python_repr {
     ($type:ty) => {
         #[pymethods]
         impl $type {
@@ -27,7 +98,14 @@ macro_rules! impl_python_other_methods {
             pub fn __repr__(&self) -> String {
                 self.__str__()
             }
+        }
+    };
+}
 
+macro_rules! impl_python_other_methods {
+    ($type:ty) => {
+        #[pymethods]
+        impl $type {
             pub fn __getstate__(&self, py: Python<'_>) -> PyObject {
                 let serialized = encode_to_vec(self, config::standard()).unwrap();
                 PyBytes::new(py, &serialized).into()
@@ -52,6 +130,56 @@ macro_rules! impl_python_other_methods {
     };
 }
 
+/// json for web clients, the same fields as the pickled state
+macro_rules! impl_json_methods {
+    ($type:ty) => {
+        #[pymethods]
+        impl $type {
+            pub fn to_json(&self) -> String {
+                serde_json::to_string(self).unwrap()
+            }
+
+            #[staticmethod]
+            pub fn from_json(s: &str) -> PyResult<Self> {
+                serde_json::from_str(s).map_err(|e| {
+                    PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())
+                })
+            }
+        }
+    };
+}
+
+/// for types that are immutable from python, consistent with __eq__
+macro_rules! impl_python_hash {
+    ($type:ty) => {
+        #[pymethods]
+        impl $type {
+            pub fn __hash__(&self) -> u64 {
+                let mut hasher = DefaultHasher::new();
+                self.hash(&mut hasher);
+                hasher.finish()
+            }
+        }
+    };
+}
+
+/// copy.copy and copy.deepcopy, both return an independent copy
+/// since these types own all of their state
+macro_rules! impl_python_copy {
+    ($type:ty) => {
+        #[pymethods]
+        impl $type {
+            pub fn __copy__(&self) -> Self {
+                self.clone()
+            }
+
+            pub fn __deepcopy__(&self, _memo: &Bound<'_, PyAny>) -> Self {
+                self.clone()
+            }
+        }
+    };
+}
+
 macro_rules! impl_python_new {
     ($type:ty) => {
         #[pymethods]
@@ -67,12 +195,18 @@ macro_rules! impl_python_new {
 
 macro_rules! impl_python_methods {
     ($type:ty) => {
+        impl_python_repr!($type);
         impl_python_other_methods!($type);
         impl_python_new!($type);
     };
     ($type:ty, skip_new) => {
+        impl_python_repr!($type);
         impl_python_other_methods!($type);
     };
+    ($type:ty, skip_repr) => {
+        impl_python_other_methods!($type);
+        impl_python_new!($type);
+    };
 }
 
 impl Debug for Tile {
@@ -101,9 +235,61 @@ impl Board {
     pub fn markers(&self) -> Vec<Option<MarkerPosition>> {
         self.markers.iter().map(|m| m.map(|m| m.position)).collect()
     }
+
+    /// the markers with previous_tile and has_moved, markers only has positions
+    #[getter]
+    pub fn marker_states(&self) -> Vec<Option<Marker>> {
+        self.markers.clone()
+    }
+}
+
+// pyo3 already adds Phase.Markers and Phase.Tiles as class attributes
+#[pymethods]
+impl Phase {
+    pub fn __int__(&self) -> i64 {
+        *self as i64
+    }
+
+    #[getter]
+    pub fn as_int(&self) -> i64 {
+        self.__int__()
+    }
 }
 
-impl_python_methods!(Board);
+// complex enums are frozen, so Move can not use __setstate__ and is
+// pickled with __reduce__ instead
+#[pymethods]
+impl Move {
+    pub fn __str__(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    pub fn __repr__(&self) -> String {
+        self.__str__()
+    }
+
+    pub fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<(Bound<'py, PyAny>, (Bound<'py, PyBytes>,))> {
+        let serialized = encode_to_vec(*slf.borrow(), config::standard()).unwrap();
+        let from_bytes = slf.py().get_type::<Move>().getattr("_from_bytes")?;
+        Ok((from_bytes, (PyBytes::new(slf.py(), &serialized),)))
+    }
+
+    #[staticmethod]
+    fn _from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        let (m, _) = decode_from_slice(bytes, config::standard()).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())
+        })?;
+        Ok(m)
+    }
+
+    pub fn __eq__(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl_python_methods!(Board, skip_repr); // __repr__ is implemented in board/render.rs
 impl_python_methods!(Tile);
 impl_python_methods!(Phase);
 impl_python_methods!(BoardGraph);
@@ -112,3 +298,73 @@ impl_python_methods!(View);
 impl_python_methods!(TsuroEnv, skip_new); // new is already implemented here
 impl_python_methods!(MarkerPosition);
 impl_python_methods!(EnvReturn);
+impl_python_methods!(Snapshot);
+impl_python_methods!(VecTsuroEnv, skip_new); // new is implemented in vec_env
+impl_python_methods!(FullView);
+impl_python_methods!(TerminationReason);
+impl_python_methods!(InvalidMoveReason);
+impl_python_methods!(RewardConfig, skip_new); // new is implemented in env.rs
+
+impl_python_hash!(MarkerPosition);
+impl_python_hash!(Tile);
+impl_python_hash!(Marker);
+impl_python_hash!(Phase);
+impl_python_hash!(TerminationReason);
+impl_python_hash!(InvalidMoveReason);
+
+impl_python_copy!(TsuroEnv);
+impl_python_copy!(Board);
+impl_python_copy!(View);
+
+impl_json_methods!(Board);
+impl_json_methods!(View);
+impl_json_methods!(TsuroEnv);
+impl_json_methods!(EnvReturn);
+
+#[test]
+fn test_json_round_trip() {
+    let mut env = TsuroEnv::new(Some(2), Some(0), EnvConfig::default()).unwrap();
+    env.step_place_marker(0).unwrap();
+    env.step_place_marker(20).unwrap();
+    let tile = env.legal_actions()[0];
+    let ret = env.step_place_tile(tile).unwrap();
+
+    assert_eq!(TsuroEnv::from_json(&env.to_json()).unwrap(), env);
+    assert_eq!(Board::from_json(&env.board.to_json()).unwrap(), env.board);
+    assert_eq!(View::from_json(&ret.view.to_json()).unwrap(), ret.view);
+    assert_eq!(EnvReturn::from_json(&ret.to_json()).unwrap(), ret);
+    assert!(Board::from_json("{}").is_err());
+}
+
+#[test]
+fn test_phase_as_int() {
+    assert_eq!(Phase::Markers.as_int(), 0);
+    assert_eq!(Phase::Tiles.as_int(), 1);
+    assert_eq!(Phase::Tiles.__int__(), 1);
+}
+
+#[test]
+fn test_hash_matches_eq() {
+    let tile = Tile::from_code("12-37-48-56").unwrap();
+    let same = Tile::from_code("12-37-48-56").unwrap();
+    assert_eq!(tile.__hash__(), same.__hash__());
+    assert_ne!(tile.__hash__(), tile.rotated(1).__hash__());
+    let position = MarkerPosition::from_index(3);
+    assert_eq!(position.__hash__(), MarkerPosition::from_index(3).__hash__());
+    assert_ne!(position.__hash__(), MarkerPosition::from_index(4).__hash__());
+    assert_ne!(Phase::Markers.__hash__(), Phase::Tiles.__hash__());
+}
+
+#[test]
+fn test_copy_is_independent() {
+    let mut env = TsuroEnv::new(Some(2), Some(0), EnvConfig::default()).unwrap();
+    env.place_random_markers(0);
+    let copy = env.__copy__();
+    assert_eq!(copy, env);
+    let tile = env.legal_actions()[0];
+    env.step_place_tile(tile).unwrap();
+    assert_ne!(copy.deck, env.deck);
+    assert_ne!(copy.player_hands, env.player_hands);
+    assert_ne!(copy.board.graph, env.board.graph);
+    assert_eq!(copy.board.tiles.as_flattened().iter().flatten().count(), 0);
+}
@@ -1,5 +1,6 @@
-use pyo3::pymethods;
-use std::cell::LazyCell;
+use itertools::Itertools;
+use pyo3::prelude::*;
+use std::sync::LazyLock;
 
 use crate::*;
 
@@ -22,6 +23,46 @@ impl Tile {
         }
     }
 
+    /// reflection across the vertical axis
+    pub fn mirror(&self) -> Self {
+        // entry point that the ith one is reflected onto
+        let reflect = |i: usize| (9 - i) % 8;
+        let mut new_conn = <[usize; 8]>::default();
+        for (i, to) in self.connections.into_iter().enumerate() {
+            new_conn[reflect(i)] = reflect(to);
+        }
+        Tile {
+            connections: new_conn,
+        }
+    }
+
+    /// the lexicographically smallest rotation, same for all rotations of a tile
+    pub fn canonical(&self) -> Self {
+        (0..4).map(|rot| self.rotated(rot)).min().unwrap()
+    }
+
+    /// wether other is one of the four rotations of this tile,
+    /// the same as comparing the canonical tiles
+    pub fn rotation_eq(&self, other: &Tile) -> bool {
+        (0..4).any(|rot| self.rotated(rot) == *other)
+    }
+
+    /// index of the tile in ALL_TILES up to rotation
+    /// None if no tile of ALL_TILES rotates into this one
+    pub fn index(&self) -> Option<usize> {
+        let canonical = self.canonical();
+        ALL_TILES
+            .iter()
+            .position(|tile| tile.canonical() == canonical)
+    }
+
+    /// number of distinct rotations of the tile (1, 2 or 4)
+    pub fn rotation_class(&self) -> usize {
+        (0..4).map(|rot| self.rotated(rot)).unique().count()
+    }
+
+    /// the four paths as pairs of entry points, the smaller one first,
+    /// sorted by the smaller one, python gets a list of tuples
     pub fn paths(&self) -> [(usize, usize); 4] {
         let mut used_vertices = [false; 8];
         let mut out = [(0, 0); 4];
@@ -52,25 +93,284 @@ fn test_rotate_tile() {
     assert_eq!(tile, tile.rotated(0))
 }
 
+#[test]
+fn test_tile_paths() {
+    assert_eq!(
+        Tile::from_code("12-34-56-78").unwrap().paths(),
+        [(0, 1), (2, 3), (4, 5), (6, 7)]
+    );
+    for tile in ALL_TILES.iter() {
+        let paths = tile.paths();
+        assert!(paths
+            .iter()
+            .all(|(a, b)| a < b && tile.connections[*a] == *b));
+        assert!(paths.is_sorted());
+    }
+}
+
+#[test]
+fn test_mirror_tile() {
+    let tile = Tile::new("12-34-57-68");
+    assert_eq!(tile.mirror(), Tile::new("12-35-46-78"));
+    for tile in ALL_TILES.iter() {
+        assert_eq!(tile.mirror().mirror(), *tile);
+        for rot in 0..4 {
+            // mirroring reverses the direction of rotation
+            assert_eq!(tile.rotated(rot).mirror(), tile.mirror().rotated(4 - rot));
+        }
+    }
+}
+
 impl Tile {
     fn new(code: &str) -> Self {
-        let s = code.split('-');
+        Self::parse_code(code).unwrap()
+    }
+
+    /// parses the dash separated format, e.g. "12-34-56-78"
+    /// each of the 8 endpoints has to appear exactly once
+    fn parse_code(code: &str) -> Result<Self, String> {
+        let pairs = code.split('-').collect_vec();
+        if pairs.len() != 4 {
+            return Err(format!("expected 4 connections in tile code {code:?}"));
+        }
         let mut connections: [usize; 8] = [0; 8];
-        for connection in s {
-            let mut c = connection.bytes();
-            let a = c.next().unwrap();
-            let b = c.next().unwrap();
-            let from = a - b'1';
-            let to = b - b'1';
-            connections[from as usize] = to as usize;
-            connections[to as usize] = from as usize;
+        let mut seen = [false; 8];
+        for pair in pairs {
+            let endpoints = pair
+                .bytes()
+                .map(|c| match c {
+                    b'1'..=b'8' => Ok((c - b'1') as usize),
+                    _ => Err(format!("invalid endpoint in tile code {code:?}")),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let [from, to] = endpoints[..] else {
+                return Err(format!("invalid connection {pair:?} in tile code"));
+            };
+            for endpoint in [from, to] {
+                if seen[endpoint] {
+                    return Err(format!(
+                        "endpoint {} appears twice in tile code {code:?}",
+                        endpoint + 1
+                    ));
+                }
+                seen[endpoint] = true;
+            }
+            connections[from] = to;
+            connections[to] = from;
         }
-        Tile { connections }
+        Ok(Tile { connections })
+    }
+}
+
+impl Tile {
+    /// svg path data of every path on the tile, drawn as quadratic béziers
+    /// corner is the top left corner of the tile, size the length of its side
+    pub(crate) fn svg_path_data(
+        &self,
+        corner: (f64, f64),
+        size: f64,
+    ) -> Vec<String> {
+        // position in the unit square and direction pointing into the tile
+        let entry_point = |idx: usize| {
+            let coords = MarkerPosition::from_entry_point_index((0, 0), idx).coords;
+            let position = (coords.0 as f64 / 3.0, coords.1 as f64 / 3.0);
+            let normal = match idx {
+                0 | 1 => (0.0, -1.0),
+                2 | 3 => (-1.0, 0.0),
+                4 | 5 => (0.0, 1.0),
+                _ => (1.0, 0.0),
+            };
+            (position, normal)
+        };
+        let to_svg = |(x, y): (f64, f64)| {
+            format!("{:.3} {:.3}", corner.0 + x * size, corner.1 + y * size)
+        };
+
+        self.paths()
+            .into_iter()
+            .map(|(a, b)| {
+                let ((a, normal_a), (b, normal_b)) =
+                    (entry_point(a), entry_point(b));
+                let middle = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+                let control = if normal_a == normal_b {
+                    // same side, bend into the tile
+                    let distance = (a.0 - b.0).abs() + (a.1 - b.1).abs();
+                    (
+                        middle.0 + normal_a.0 * distance,
+                        middle.1 + normal_a.1 * distance,
+                    )
+                } else if normal_a.0 == -normal_b.0 && normal_a.1 == -normal_b.1 {
+                    // opposite sides, straight line
+                    middle
+                } else if normal_a.0 == 0.0 {
+                    // adjacent sides, where the lines going into the tile meet
+                    (a.0, b.1)
+                } else {
+                    (b.0, a.1)
+                };
+                format!("M {} Q {} {}", to_svg(a), to_svg(control), to_svg(b))
+            })
+            .collect()
+    }
+}
+
+#[pymethods]
+impl Tile {
+    /// svg path data of every path in the unit square,
+    /// entry points are a third of the side apart
+    pub fn to_svg_paths(&self) -> Vec<String> {
+        self.svg_path_data((0.0, 0.0), 1.0)
+    }
+
+    #[staticmethod]
+    pub fn from_code(code: &str) -> PyResult<Tile> {
+        Self::parse_code(code)
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+    }
+
+    /// the dash separated format accepted by from_code
+    pub fn code(&self) -> String {
+        self.paths()
+            .iter()
+            .map(|(a, b)| format!("{}{}", a + 1, b + 1))
+            .join("-")
+    }
+
+    /// connections packed into 24 bits, entry point i in bits 3 * i to 3 * i + 2
+    pub fn to_u32(&self) -> u32 {
+        self.connections
+            .iter()
+            .enumerate()
+            .map(|(i, to)| (*to as u32) << (3 * i))
+            .sum()
+    }
+
+    /// inverse of to_u32, every entry point has to be connected to
+    /// a different one that is connected back to it
+    #[staticmethod]
+    pub fn from_u32(value: u32) -> PyResult<Tile> {
+        let invalid = || {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "{value:#x} does not encode a tile"
+            ))
+        };
+        if value >> 24 != 0 {
+            return Err(invalid());
+        }
+        let connections: [usize; 8] =
+            std::array::from_fn(|i| ((value >> (3 * i)) & 0b111) as usize);
+        let paired = connections
+            .iter()
+            .enumerate()
+            .all(|(i, to)| *to != i && connections[*to] == i);
+        if !paired {
+            return Err(invalid());
+        }
+        Ok(Tile { connections })
+    }
+}
+
+#[test]
+fn test_tile_u32_round_trip() {
+    for tile in ALL_TILES.iter() {
+        for rot in 0..4 {
+            let tile = tile.rotated(rot);
+            assert_eq!(Tile::from_u32(tile.to_u32()).unwrap(), tile);
+        }
+    }
+    let codes = ALL_TILES.iter().map(Tile::to_u32).collect_vec();
+    assert!(codes.iter().all_unique());
+    assert!(Tile::from_u32(0).is_err()); // everything connected to 0
+    assert!(Tile::from_u32(1 << 24).is_err());
+    let one_sided = (ALL_TILES[0].to_u32() & !0b111) | 2;
+    assert!(Tile::from_u32(one_sided).is_err());
+}
+
+#[test]
+fn test_canonical_tile() {
+    for (idx, tile) in ALL_TILES.iter().enumerate() {
+        let canonical = tile.canonical();
+        assert_eq!(canonical.canonical(), canonical);
+        for rot in 0..4 {
+            assert_eq!(tile.rotated(rot).canonical(), canonical);
+        }
+        // ALL_TILES is ordered by symmetry
+        let expected_class = match idx {
+            0..5 => 1,
+            5..15 => 2,
+            _ => 4,
+        };
+        assert_eq!(tile.rotation_class(), expected_class);
+    }
+}
+
+#[test]
+fn test_rotation_eq() {
+    for (a, b) in ALL_TILES.iter().cartesian_product(ALL_TILES.iter()) {
+        for rot in 0..4 {
+            let rotated = b.rotated(rot);
+            assert_eq!(a.rotation_eq(&rotated), a == b);
+            assert_eq!(
+                a.rotation_eq(&rotated),
+                a.canonical() == rotated.canonical()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_tile_index() {
+    for (idx, tile) in ALL_TILES.iter().enumerate() {
+        for rot in 0..4 {
+            assert_eq!(tile.rotated(rot).index(), Some(idx));
+        }
+    }
+    let invalid = Tile {
+        connections: [0; 8],
+    };
+    assert_eq!(invalid.index(), None);
+}
+
+#[test]
+fn test_tile_from_code() {
+    assert_eq!(
+        Tile::from_code("18-23-45-67").unwrap().connections,
+        [7, 2, 1, 4, 3, 6, 5, 0]
+    );
+    assert!(Tile::from_code("12-12-56-78").is_err());
+    assert!(Tile::from_code("12-34-56-79").is_err());
+    assert!(Tile::from_code("12-34-56").is_err());
+    assert!(Tile::from_code("12-34-56-7").is_err());
+    for tile in ALL_TILES.iter() {
+        assert_eq!(Tile::from_code(&tile.code()).unwrap(), *tile);
+    }
+    assert_eq!(Tile::new("18-23-45-67").code(), "18-23-45-67");
+}
+
+#[test]
+fn test_tile_to_svg_paths() {
+    // 12-34-56-78 connects neighbouring entry points on the same side
+    for data in Tile::from_code("12-34-56-78").unwrap().to_svg_paths() {
+        let numbers = data
+            .split(' ')
+            .filter_map(|part| part.parse::<f64>().ok())
+            .collect_vec();
+        let (start, end) = ((numbers[0], numbers[1]), (numbers[4], numbers[5]));
+        assert!(start.0 == end.0 || start.1 == end.1);
     }
+    let straight = Tile::from_code("16-25-38-47").unwrap().to_svg_paths();
+    assert_eq!(
+        straight,
+        [
+            "M 0.333 1.000 Q 0.333 0.500 0.333 0.000",
+            "M 0.667 1.000 Q 0.667 0.500 0.667 0.000",
+            "M 1.000 0.667 Q 0.500 0.667 0.000 0.667",
+            "M 1.000 0.333 Q 0.500 0.333 0.000 0.333",
+        ]
+    );
 }
 
-#[allow(clippy::declare_interior_mutable_const)]
-pub const ALL_TILES: LazyCell<[Tile; 35]> = LazyCell::new(|| {
+pub static ALL_TILES: LazyLock<[Tile; 35]> = LazyLock::new(|| {
     [
         Tile::new("12-34-56-78"),
         Tile::new("14-27-36-58"),
@@ -0,0 +1,154 @@
+use crate::*;
+
+use itertools::Itertools;
+use pyo3_tch::PyTensor;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use tch::Tensor;
+
+#[pymethods]
+impl VecTsuroEnv {
+    /// takes Options so calling __new__() works, like TsuroEnv
+    /// env i is seeded with seed + i, every env uses reward_config
+    #[new]
+    #[pyo3(signature = (num_envs=None, num_players=None, seed=None, *, hand_size=3, reward_config=None))]
+    fn py_new(
+        num_envs: Option<usize>,
+        num_players: Option<usize>,
+        seed: Option<u64>,
+        hand_size: usize,
+        reward_config: Option<RewardConfig>,
+    ) -> PyResult<Self> {
+        let config = EnvConfig {
+            hand_size,
+            reward_config: reward_config.unwrap_or_default(),
+            ..Default::default()
+        };
+        Self::new(num_envs, num_players, seed, config)
+    }
+
+    #[getter]
+    pub fn num_envs(&self) -> usize {
+        self.envs.len()
+    }
+
+    pub fn reset_all(&mut self) -> Vec<EnvReturn> {
+        self.envs.iter_mut().map(TsuroEnv::reset).collect()
+    }
+
+    /// positions[i] is placed in env i
+    /// envs that are not in the marker phase ignore their position
+    pub fn step_place_marker_all(
+        &mut self,
+        positions: Vec<usize>,
+    ) -> PyResult<Vec<EnvReturn>> {
+        self.step_all(positions, |env, position| {
+            if env.phase != Phase::Markers {
+                return Ok(env.get_return(false));
+            }
+            env.step_place_marker(position)
+        })
+    }
+
+    /// tiles[i] is placed in env i
+    /// envs that are not in the tile phase ignore their tile
+    /// terminated envs are reset and start again from the marker phase,
+    /// the returned value is still the last one of the finished game
+    pub fn step_place_tile_all(
+        &mut self,
+        tiles: Vec<Tile>,
+    ) -> PyResult<Vec<EnvReturn>> {
+        self.step_all(tiles, |env, tile| {
+            if env.phase != Phase::Tiles {
+                return Ok(env.get_return(false));
+            }
+            let ret = env.step_place_tile(tile)?;
+            if ret.terminated {
+                env.reset();
+            }
+            Ok(ret)
+        })
+    }
+
+    /// Board::tile_tensor of each env stacked,
+    /// shape [num_envs, BOARD_SIZE, BOARD_SIZE, 36]
+    pub fn tile_tensors(&self) -> PyTensor {
+        let tensors = self.envs.iter().map(|env| env.board.tile_tensor().0);
+        PyTensor(Tensor::stack(&tensors.collect_vec(), 0))
+    }
+
+    /// Board::marker_tensor of each env stacked,
+    /// shape [num_envs, num_players, LATTICE_SIZE, LATTICE_SIZE]
+    pub fn marker_tensors(&self) -> PyTensor {
+        let tensors = self
+            .envs
+            .iter()
+            .map(|env| env.board.marker_tensor(env.num_players).0);
+        PyTensor(Tensor::stack(&tensors.collect_vec(), 0))
+    }
+}
+
+impl VecTsuroEnv {
+    /// every env is made with the same config
+    pub fn new(
+        num_envs: Option<usize>,
+        num_players: Option<usize>,
+        seed: Option<u64>,
+        config: EnvConfig,
+    ) -> PyResult<Self> {
+        let envs = (0..num_envs.unwrap_or(0))
+            .map(|i| TsuroEnv::new(num_players, seed.map(|s| s + i as u64), config))
+            .collect::<PyResult<_>>()?;
+        Ok(Self { envs })
+    }
+
+    fn step_all<A: Send>(
+        &mut self,
+        actions: Vec<A>,
+        step: impl Fn(&mut TsuroEnv, A) -> PyResult<EnvReturn> + Sync,
+    ) -> PyResult<Vec<EnvReturn>> {
+        if actions.len() != self.envs.len() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "expected {} actions, got {}",
+                self.envs.len(),
+                actions.len()
+            )));
+        }
+        #[cfg(feature = "rayon")]
+        let envs = self.envs.par_iter_mut().zip(actions);
+        #[cfg(not(feature = "rayon"))]
+        let envs = self.envs.iter_mut().zip(actions);
+        envs.map(|(env, action)| step(env, action)).collect()
+    }
+}
+
+#[test]
+fn test_vec_env() {
+    let mut vec_env =
+        VecTsuroEnv::new(Some(3), Some(2), Some(0), EnvConfig::default()).unwrap();
+    assert!(vec_env
+        .step_place_tile_all(vec![Tile::default(); 2])
+        .is_err());
+    vec_env.step_place_marker_all(vec![0, 1, 2]).unwrap();
+    vec_env.step_place_marker_all(vec![30, 31, 32]).unwrap();
+    let (board, lattice) = (BOARD_SIZE as i64, LATTICE_SIZE as i64);
+    assert_eq!(vec_env.tile_tensors().size(), [3, board, board, 36]);
+    assert_eq!(vec_env.marker_tensors().size(), [3, 2, lattice, lattice]);
+
+    let mut num_terminated = 0;
+    while num_terminated == 0 {
+        let tiles = vec_env
+            .envs
+            .iter()
+            .map(|env| env.legal_actions().first().copied().unwrap_or_default())
+            .collect();
+        let rets = vec_env.step_place_tile_all(tiles).unwrap();
+        for (env, ret) in vec_env.envs.iter().zip(rets) {
+            if ret.terminated {
+                num_terminated += 1;
+                assert_eq!(env.phase, Phase::Markers);
+                assert_eq!(env.board, Board::new());
+            }
+        }
+    }
+}
@@ -1,6 +1,10 @@
 use itertools::Itertools;
 use pyo3::prelude::*;
+use pyo3_tch::PyTensor;
+use rand::{seq::IndexedRandom, SeedableRng};
+use rand_chacha::ChaCha12Rng;
 use std::collections::VecDeque;
+use tch::Tensor;
 
 use crate::*;
 
@@ -9,10 +13,12 @@ impl BoardGraph {
     /// run a bfs from node_id and return a list of (dist, node)
     /// dist: distance from node_id
     /// node: next node id
-    pub fn bfs_from(&self, node_id: usize) -> Vec<(usize, usize)> {
+    /// built_only: only follow edges of paths drawn on placed tiles
+    #[pyo3(signature = (node_id, built_only=false))]
+    pub fn bfs_from(&self, node_id: usize, built_only: bool) -> Vec<(usize, usize)> {
         let mut out = Vec::with_capacity(self.vertices.len());
         let mut queue = VecDeque::new();
-        let mut visited = [false; 168];
+        let mut visited = [false; NUM_NODES];
 
         queue.push_back((node_id, 0));
         visited[node_id] = true;
@@ -22,8 +28,8 @@ impl BoardGraph {
             let (current_id, current_dist) = queue.pop_front().unwrap();
             let next_dist = current_dist + 1;
 
-            for (to_id, _built) in &self.adjacency_list[current_id] {
-                if visited[*to_id] {
+            for (to_id, built) in &self.adjacency_list[current_id] {
+                if visited[*to_id] || (built_only && !built) {
                     continue;
                 }
                 visited[*to_id] = true;
@@ -33,6 +39,91 @@ impl BoardGraph {
         }
         out
     }
+
+    /// number of nodes reachable from start_node over built and unbuilt edges,
+    /// including start_node, 0 if start_node is not in the graph
+    pub fn reachable_count(&self, start_node: usize) -> usize {
+        if !matches!(self.vertices.get(start_node), Some(Some(_))) {
+            return 0;
+        }
+        self.bfs_from(start_node, false).len()
+    }
+
+    /// component label of every node id, usize::MAX for removed nodes
+    /// components are numbered in order of their smallest node id
+    /// built_only: only follow edges of paths drawn on placed tiles
+    pub fn connected_components(&self, built_only: bool) -> Vec<usize> {
+        let mut labels = vec![usize::MAX; NUM_NODES];
+        let mut num_components = 0;
+        for (node_id, vertex) in self.vertices.iter().enumerate() {
+            if vertex.is_none() || labels[node_id] != usize::MAX {
+                continue;
+            }
+            for (_dist, id) in self.bfs_from(node_id, built_only) {
+                labels[id] = num_components;
+            }
+            num_components += 1;
+        }
+        labels
+    }
+
+    /// number of independent cycles, edges - nodes + components
+    /// with built_only these are the closed loops drawn by the placed tiles,
+    /// every path is a single edge so a loop is an edge from a node to itself
+    pub fn count_loops(&self, built_only: bool) -> usize {
+        let num_nodes = self.vertices.iter().filter(|v| v.is_some()).count();
+        // every edge is listed on both of its ends, a loop twice on its node
+        let num_edge_ends = self
+            .adjacency_list
+            .iter()
+            .flatten()
+            .filter(|(_to_id, built)| *built || !built_only)
+            .count();
+        let num_components = self
+            .connected_components(built_only)
+            .into_iter()
+            .filter(|label| *label != usize::MAX)
+            .max()
+            .map_or(0, |label| label + 1);
+        num_edge_ends / 2 + num_components - num_nodes
+    }
+
+    /// distance and node ids along a shortest path from `from` to `to`
+    /// built_only: only follow edges of paths drawn on placed tiles
+    /// None if `to` can not be reached
+    pub fn shortest_path(
+        &self,
+        from: usize,
+        to: usize,
+        built_only: bool,
+    ) -> Option<(usize, Vec<usize>)> {
+        let mut queue = VecDeque::new();
+        let mut parent = [None; NUM_NODES];
+        let mut visited = [false; NUM_NODES];
+
+        queue.push_back(from);
+        visited[from] = true;
+
+        while let Some(current_id) = queue.pop_front() {
+            if current_id == to {
+                let mut path = vec![to];
+                while let Some(prev_id) = parent[*path.last().unwrap()] {
+                    path.push(prev_id);
+                }
+                path.reverse();
+                return Some((path.len() - 1, path));
+            }
+            for (to_id, built) in &self.adjacency_list[current_id] {
+                if visited[*to_id] || (built_only && !built) {
+                    continue;
+                }
+                visited[*to_id] = true;
+                parent[*to_id] = Some(current_id);
+                queue.push_back(*to_id);
+            }
+        }
+        None
+    }
 }
 
 #[pymethods]
@@ -46,27 +137,500 @@ impl View {
             .collect()
     }
 
+    /// number of tiles in the active player's hand
+    pub fn hand_size(&self) -> usize {
+        self.hand.len()
+    }
+
+    /// wether the active player holds the tile in any rotation
+    pub fn has_tile(&self, tile: Tile) -> bool {
+        self.hand
+            .iter()
+            .any(|hand_tile| hand_tile.rotation_eq(&tile))
+    }
+
+    /// wether the active player may place the tile, it has to be a rotation
+    /// of a hand tile and may only be suicide if every move is
+    pub fn is_move_legal(&self, tile: Tile) -> PyResult<bool> {
+        self.board.require_marker(self.active_player)?;
+        let tiles = self.all_rotated_tiles();
+        let is_suicide =
+            |tile: &Tile| self.board.move_is_suicide(*tile, self.active_player);
+        Ok(tiles.contains(&tile)
+            && (!is_suicide(&tile) || tiles.iter().all(is_suicide)))
+    }
+
+    /// number of distinct rotated hand tiles that are not suicide
+    /// 0 means every move eliminates the active player
+    pub fn num_safe_moves(&self) -> usize {
+        if self.board.marker_of(self.active_player).is_none() {
+            return 0;
+        }
+        self.all_rotated_tiles()
+            .into_iter()
+            .filter(|tile| !self.board.move_is_suicide(*tile, self.active_player))
+            .count()
+    }
+
+    /// cell the active player's next tile is placed on,
+    /// None if the player has no marker on the board
+    pub fn next_tile_cell(&self) -> Option<Coord> {
+        self.board.marker_of(self.active_player)?;
+        Some(self.board.next_tile_of_player(self.active_player))
+    }
+
+    /// marker one-hot like Board::marker_tensor,
+    /// shape [num_players, LATTICE_SIZE, LATTICE_SIZE]
+    /// channel 0 is the active player, the other players follow in turn order
+    /// eliminated players have all zero channels
+    pub fn marker_tensor_egocentric(&self) -> PyTensor {
+        let num_players = self.board.markers.len();
+        let mut one_hot = vec![0i8; num_players * LATTICE_SIZE * LATTICE_SIZE];
+        for channel in 0..num_players {
+            let player = (self.active_player + channel) % num_players;
+            if let Some(marker) = self.board.markers[player] {
+                let (x, y) = marker.position.coords;
+                one_hot[(channel * LATTICE_SIZE + x) * LATTICE_SIZE + y] = 1;
+            }
+        }
+        let size = LATTICE_SIZE as i64;
+        PyTensor(Tensor::from_slice(&one_hot).view([num_players as i64, size, size]))
+    }
+
+    /// count of each tile of ALL_TILES in the hand, shape [35]
+    /// rotations of the same tile count towards the same entry
+    pub fn hand_tensor(&self) -> PyTensor {
+        let mut counts = vec![0i8; ALL_TILES.len()];
+        for tile in &self.hand {
+            let index = tile
+                .index()
+                .expect("every tile should be a rotation of a tile in ALL_TILES");
+            counts[index] += 1;
+        }
+        PyTensor(Tensor::from_slice(&counts))
+    }
+
+    /// the board after the active player places the tile and the markers moved,
+    /// and the players eliminated by colliding or reaching the edge
+    pub fn simulate_placement(&self, tile: Tile) -> PyResult<(Board, Vec<usize>)> {
+        if !self.is_move_legal(tile)? {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "{tile:?} is not a legal move for player {}",
+                self.active_player
+            )));
+        }
+        let mut board = self.board.clone();
+        let tile_pos = board.next_tile_of_player(self.active_player);
+        // before moving the markers, find_collisions sees them next to the tile
+        let mut eliminated = board.find_collisions(tile, tile_pos);
+        board.place_tile(tile, self.active_player);
+        for player in &eliminated {
+            board.eliminate_player(*player);
+        }
+        for player in board.move_markers() {
+            board.eliminate_player(player);
+            eliminated.push(player);
+        }
+        eliminated.sort_unstable();
+        Ok((board, eliminated))
+    }
+
+    /// scripted baseline: the legal tile after which the active player's
+    /// path is longest (see Board::path_length), ties go to the tile that
+    /// comes first in all_rotated_tiles
+    /// None if the hand is empty or the active player has no marker
+    pub fn greedy_action(&self) -> Option<Tile> {
+        self.board.marker_of(self.active_player)?;
+        let mut best: Option<(Tile, usize)> = None;
+        for tile in self.candidate_tiles() {
+            let (_tile, board) = self.afterstate(tile);
+            let length = board.path_length(self.active_player);
+            if best.is_none_or(|(_tile, best_length)| length > best_length) {
+                best = Some((tile, length));
+            }
+        }
+        best.map(|(tile, _length)| tile)
+    }
+
+    /// uniformly random legal tile, the same seed always gives the same tile
+    /// None if the hand is empty or the active player has no marker
+    pub fn random_action(&self, seed: u64) -> Option<Tile> {
+        self.board.marker_of(self.active_player)?;
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+        self.candidate_tiles().choose(&mut rng).copied()
+    }
+
+    /// afterstates with each board in all four orientations for data augmentation,
+    /// (tile, board, r) is turned r times by Board.rotate90, the tile as well,
+    /// so it is the move that leads to the board in that orientation
+    pub fn afterstates_augmented(&self) -> Vec<(Tile, Board, usize)> {
+        let mut augmented = vec![];
+        for (tile, board) in self.afterstates() {
+            let mut board = board;
+            for rotation in 0..4 {
+                let next = board.rotate90();
+                augmented.push((tile.rotated(rotation), board, rotation));
+                board = next;
+            }
+        }
+        augmented
+    }
+
+    /// the board after placing each tile of all_rotated_tiles
+    /// suicide moves are left out unless every move is suicide
+    /// runs on multiple threads if the rayon feature is enabled
     pub fn afterstates(&self) -> Vec<(Tile, Board)> {
-        let mut out = vec![];
+        #[cfg(feature = "rayon")]
+        return self.afterstates_parallel();
+        #[cfg(not(feature = "rayon"))]
+        return self.afterstates_serial();
+    }
+}
+
+impl View {
+    fn candidate_tiles(&self) -> Vec<Tile> {
         let tiles = self.all_rotated_tiles();
         let is_suicide: Vec<_> = tiles
             .iter()
             .map(|tile| self.board.move_is_suicide(*tile, self.active_player))
             .collect();
         let all_suicide = is_suicide.iter().all(|x| *x);
-        for (tile, suicide) in tiles.into_iter().zip(is_suicide) {
-            if suicide && !all_suicide {
-                continue;
-            }
-            let mut board = self.board.clone();
-            board.place_tile(tile, self.active_player);
-            let eliminated = board.move_markers();
-            for player in eliminated {
-                board.eliminate_player(player);
-            }
-            out.push((tile, board));
+        tiles
+            .into_iter()
+            .zip(is_suicide)
+            .filter(|(_tile, suicide)| all_suicide || !suicide)
+            .map(|(tile, _suicide)| tile)
+            .collect()
+    }
+
+    fn afterstate(&self, tile: Tile) -> (Tile, Board) {
+        let mut board = self.board.clone();
+        board.place_tile(tile, self.active_player);
+        let eliminated = board.move_markers();
+        for player in eliminated {
+            board.eliminate_player(player);
         }
+        (tile, board)
+    }
 
-        out
+    #[cfg_attr(feature = "rayon", allow(dead_code))]
+    fn afterstates_serial(&self) -> Vec<(Tile, Board)> {
+        self.candidate_tiles()
+            .into_iter()
+            .map(|tile| self.afterstate(tile))
+            .collect()
+    }
+
+    #[cfg(feature = "rayon")]
+    fn afterstates_parallel(&self) -> Vec<(Tile, Board)> {
+        use rayon::prelude::*;
+
+        self.candidate_tiles()
+            .into_par_iter()
+            .map(|tile| self.afterstate(tile))
+            .collect()
+    }
+}
+
+#[test]
+fn test_marker_tensor_egocentric() {
+    let mut board = Board::new();
+    for position in [0, 5, 9] {
+        board.place_marker(position);
+    }
+    board.eliminate_player(2);
+    let view = View {
+        board: board.clone(),
+        hand: vec![],
+        active_player: 1,
+    };
+    let tensor = view.marker_tensor_egocentric().0;
+    let size = LATTICE_SIZE as i64;
+    assert_eq!(tensor.size(), [3, size, size]);
+    assert_eq!(tensor.sum(tch::Kind::Int64).int64_value(&[]), 2);
+    let (x, y) = MarkerPosition::from_index(5).coords;
+    assert_eq!(tensor.int64_value(&[0, x as i64, y as i64]), 1);
+    // player 2 is eliminated and player 0 comes after it
+    assert_eq!(tensor.get(1).sum(tch::Kind::Int64).int64_value(&[]), 0);
+    let (x, y) = MarkerPosition::from_index(0).coords;
+    assert_eq!(tensor.int64_value(&[2, x as i64, y as i64]), 1);
+}
+
+#[test]
+fn test_hand_tensor() {
+    let view = View {
+        board: Board::new(),
+        hand: vec![ALL_TILES[3], ALL_TILES[20].rotated(1), ALL_TILES[20]],
+        active_player: 0,
+    };
+    let tensor = view.hand_tensor().0;
+    assert_eq!(tensor.size(), [35]);
+    assert_eq!(tensor.sum(tch::Kind::Int64).int64_value(&[]), 3);
+    assert_eq!(tensor.int64_value(&[3]), 1);
+    assert_eq!(tensor.int64_value(&[20]), 2);
+}
+
+#[test]
+fn test_num_safe_moves() {
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_marker(20);
+    // every rotation is the same tile for both
+    let straight = Tile::from_code("16-25-38-47").unwrap();
+    let u_turn = Tile::from_code("12-34-56-78").unwrap();
+    let mut view = View {
+        board,
+        hand: vec![straight, u_turn],
+        active_player: 0,
+    };
+    assert_eq!(view.all_rotated_tiles().len(), 2);
+    assert_eq!(view.num_safe_moves(), 1);
+    view.hand = vec![u_turn];
+    assert_eq!(view.num_safe_moves(), 0);
+
+    view.hand = vec![straight];
+    view.board.eliminate_player(0);
+    assert_eq!(view.num_safe_moves(), 0);
+}
+
+#[test]
+fn test_is_move_legal() {
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_marker(20);
+    let straight = Tile::from_code("16-25-38-47").unwrap();
+    let u_turn = Tile::from_code("12-34-56-78").unwrap();
+    let mut view = View {
+        board,
+        hand: vec![straight, u_turn],
+        active_player: 0,
+    };
+    assert!(view.is_move_legal(straight).unwrap());
+    assert!(!view.is_move_legal(u_turn).unwrap());
+    assert!(!view
+        .is_move_legal(Tile::from_code("13-24-57-68").unwrap())
+        .unwrap());
+    // suicide is legal when there is nothing else
+    view.hand = vec![u_turn];
+    assert!(view.is_move_legal(u_turn).unwrap());
+
+    view.board.eliminate_player(0);
+    assert!(view.is_move_legal(u_turn).is_err());
+}
+
+/// run with cargo test --release --features rayon -- --ignored --nocapture
+#[cfg(feature = "rayon")]
+#[test]
+#[ignore]
+fn bench_afterstates_serial_vs_parallel() {
+    use std::hint::black_box;
+    use std::time::Instant;
+
+    let mut env = TsuroEnv::new(Some(4), Some(0), EnvConfig::default()).unwrap();
+    for position in [0, 12, 24, 36] {
+        env.step_place_marker(position).unwrap();
+    }
+    for _ in 0..10 {
+        let tile = env.legal_actions()[0];
+        env.step_place_tile(tile).unwrap();
+    }
+    let view = View {
+        board: env.board.clone(),
+        hand: env.player_hands[env.active_player].clone(),
+        active_player: env.active_player,
+    };
+    assert_eq!(view.afterstates_serial(), view.afterstates_parallel());
+    let iterations = 10_000;
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(view.afterstates_serial());
+    }
+    println!("serial:   {:?}", start.elapsed() / iterations);
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(view.afterstates_parallel());
+    }
+    println!("parallel: {:?}", start.elapsed() / iterations);
+}
+
+#[test]
+fn test_simulate_placement() {
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_marker(20);
+    let u_turn = Tile::from_code("12-34-56-78").unwrap();
+    let straight = Tile::from_code("16-25-38-47").unwrap();
+    let view = View {
+        board,
+        hand: vec![u_turn, straight],
+        active_player: 0,
+    };
+    let (board, eliminated) = view.simulate_placement(straight).unwrap();
+    assert!(eliminated.is_empty());
+    let (_tile, expected) = view.afterstate(straight);
+    assert_eq!(board, expected);
+    assert!(view.simulate_placement(u_turn).is_err()); // suicide
+    assert!(view
+        .simulate_placement(find_tile_with_connection(0, 7))
+        .is_err());
+
+    let view = View {
+        hand: vec![u_turn],
+        ..view
+    };
+    let (board, eliminated) = view.simulate_placement(u_turn).unwrap();
+    assert_eq!(eliminated, vec![0]);
+    assert_eq!(board.markers[0], None);
+}
+
+#[test]
+fn test_greedy_action() {
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_marker(20);
+    let u_turn = Tile::from_code("12-34-56-78").unwrap();
+    let view = View {
+        board,
+        hand: vec![],
+        active_player: 0,
+    };
+    assert_eq!(view.greedy_action(), None);
+    let view = View {
+        hand: vec![u_turn],
+        ..view
+    };
+    // suicide is the only move
+    assert_eq!(view.greedy_action(), Some(u_turn));
+
+    for seed in 0..5 {
+        let mut env =
+            TsuroEnv::new(Some(3), Some(seed), EnvConfig::default()).unwrap();
+        for position in [0, 16, 32] {
+            env.step_place_marker(position).unwrap();
+        }
+        while !env.legal_actions().is_empty() {
+            let view = View {
+                board: env.board.clone(),
+                hand: env.player_hands[env.active_player].clone(),
+                active_player: env.active_player,
+            };
+            let tile = view.greedy_action().unwrap();
+            assert!(view.is_move_legal(tile).unwrap());
+            assert!(env.step_place_tile(tile).unwrap().move_is_valid);
+        }
+    }
+}
+
+#[test]
+fn test_random_action() {
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_marker(20);
+    let view = View {
+        board,
+        hand: vec![],
+        active_player: 0,
+    };
+    assert_eq!(view.random_action(0), None);
+
+    let view = View {
+        hand: ALL_TILES[10..13].to_vec(),
+        ..view
+    };
+    let mut seen = std::collections::HashSet::new();
+    for seed in 0..50 {
+        let tile = view.random_action(seed).unwrap();
+        assert_eq!(view.random_action(seed), Some(tile));
+        assert!(view.is_move_legal(tile).unwrap());
+        seen.insert(tile);
+    }
+    assert!(seen.len() > 1);
+}
+
+#[test]
+fn test_next_tile_cell() {
+    let mut board = Board::new();
+    board.place_marker(0);
+    let mut view = View {
+        board,
+        hand: vec![],
+        active_player: 1,
+    };
+    assert_eq!(view.next_tile_cell(), None);
+    view.active_player = 0;
+    assert_eq!(view.next_tile_cell(), Some((0, BOARD_SIZE - 1)));
+
+    let straight = Tile::from_code("16-25-38-47").unwrap();
+    view.board.place_tile(straight, 0);
+    view.board.move_markers();
+    assert_eq!(view.next_tile_cell(), Some((0, BOARD_SIZE - 2)));
+    view.board.eliminate_player(0);
+    assert_eq!(view.next_tile_cell(), None);
+}
+
+#[test]
+fn test_hand_size_and_has_tile() {
+    let u_turn = Tile::from_code("12-34-56-78").unwrap();
+    let straight = Tile::from_code("16-25-38-47").unwrap();
+    let mut view = View {
+        board: Board::new(),
+        hand: vec![],
+        active_player: 0,
+    };
+    assert_eq!(view.hand_size(), 0);
+    assert!(!view.has_tile(u_turn));
+
+    let tile = ALL_TILES[5];
+    view.hand = vec![u_turn, tile];
+    assert_eq!(view.hand_size(), 2);
+    assert!(view.has_tile(u_turn));
+    assert!((0..4).all(|rot| view.has_tile(tile.rotated(rot))));
+    assert!(!view.has_tile(straight));
+}
+
+#[test]
+fn test_count_loops() {
+    assert_eq!(Board::new().graph.count_loops(true), 0);
+    // facing u turns on neighbouring tiles close a loop on their shared side
+    let u_turn = Tile::from_code("12-34-56-78").unwrap();
+    let loops = |cells: &[Coord]| {
+        let placements = cells.iter().map(|cell| (*cell, u_turn)).collect();
+        Board::from_placements(placements, vec![])
+            .unwrap()
+            .graph
+            .count_loops(true)
+    };
+    assert_eq!(loops(&[(1, 1)]), 0);
+    assert_eq!(loops(&[(1, 1), (2, 1)]), 1);
+    assert_eq!(loops(&[(1, 1), (2, 1), (1, 2), (2, 2)]), 4);
+
+    // the unbuilt graph of the empty board is full of cycles
+    let graph = Board::new().graph;
+    assert!(graph.count_loops(false) > 0);
+    let num_edges = graph.adjacency_list.iter().flatten().count() / 2;
+    assert_eq!(graph.count_loops(false), num_edges + 1 - NUM_NODES);
+}
+
+#[test]
+fn test_afterstates_augmented() {
+    let mut board = Board::new();
+    board.place_marker(0);
+    board.place_marker(20);
+    let view = View {
+        board,
+        hand: ALL_TILES[10..13].to_vec(),
+        active_player: 0,
+    };
+    let afterstates = view.afterstates();
+    let augmented = view.afterstates_augmented();
+    assert_eq!(augmented.len(), afterstates.len() * 4);
+    for (idx, (tile, board)) in afterstates.into_iter().enumerate() {
+        let rotations = &augmented[idx * 4..idx * 4 + 4];
+        assert_eq!(rotations[0], (tile, board.clone(), 0));
+        assert_eq!(rotations[1].1, board.rotate90());
+        assert_eq!(rotations[3].0, tile.rotated(3));
+        assert!(rotations.iter().map(|(_, _, r)| *r).eq(0..4));
     }
 }